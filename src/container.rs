@@ -1,26 +1,158 @@
-use errors::{self, Error};
+use errors::{self, BuildError, Error, RenameError, TransferError};
 use methods::Method;
 use reflect;
 
 use downcast::{self, Downcast};
 
-use std::any::Any;
-use std::collections::BTreeMap;
-use std::sync::{TryLockError, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error as StdError;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, RangeBounds};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, Once, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
 
-fn type_name<T: Any>() -> &'static str {
-    "NOT IMPLEMENTED"
-    //unsafe { ::std::intrinsics::type_name::<T>() }
+fn type_name<T: ?Sized>() -> &'static str {
+    ::std::any::type_name::<T>()
+}
+
+// Whether `key` is an empty or whitespace-only string; such keys usually stem from a copy-paste 
+// mistake in a `Service::key` impl and would only show up as confusing `NotFound` errors later on.
+fn is_blank<Key: Any>(key: &Key) -> bool {
+    let key: &dyn Any = key;
+    key.downcast_ref::<String>().map(String::as_str)
+        .or_else(|| key.downcast_ref::<&str>().cloned())
+        .is_some_and(|key| key.trim().is_empty())
+}
+
+fn downcast_service_mut<'a, Key, SvcBase, Svc>(
+    key: &'a Key,
+    service: &'a mut RwLock<Box<SvcBase>>,
+) -> Result<&'a mut Svc, Error<'a, Key>>
+    where Key: reflect::Key, Svc: Any, SvcBase: ?Sized + Downcast<Svc>
+{
+    let base = errors::or_err(key, service.get_mut())?;
+    match Downcast::<Svc>::downcast_mut(&mut **base) {
+        Some(svc) => Ok(svc),
+        None => Err(Error::MismatchedType{ 
+            key, 
+            expected: type_name::<Svc>(),
+            found: type_name::<Svc>(),
+        })
+    }
 }
 
 // ++++++++++++++++++++ Container ++++++++++++++++++++
 
-pub type ReadGuard<'a, T, Base: ?Sized> = downcast::Guard<T, RwLockReadGuard<'a, Box<Base>>>;
-pub type WriteGuard<'a, T, Base: ?Sized> = downcast::Guard<T, RwLockWriteGuard<'a, Box<Base>>>;
+pub type ReadGuard<'a, T, Base> = downcast::Guard<T, RwLockReadGuard<'a, Box<Base>>>;
+pub type WriteGuard<'a, T, Base> = downcast::Guard<T, RwLockWriteGuard<'a, Box<Base>>>;
+
+/// Hook run by `Container` on every lookup, see `ContainerBuilder::with_middleware`.
+pub type Middleware<Key, SvcBase> = Box<dyn Fn(&Key, &SvcBase) -> bool + Send + Sync>;
+
+/// Warning run the first time a deprecated key gets resolved, with the deprecated key and its 
+/// replacement; see `ContainerBuilder::deprecate`.
+pub type DeprecationWarning<Key> = Box<dyn Fn(&Key, &Key) + Send + Sync>;
+
+/// Change to the set of services, as reported to subscribers; see `ContainerBuilder::subscribe`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerEvent<'e, Key: 'e> {
+    Registered{ key: &'e Key },
+    Renamed{ old: &'e Key, new: &'e Key },
+    Removed{ key: &'e Key },
+}
+
+/// Observer of `ContainerEvent`s, see `ContainerBuilder::subscribe`.
+pub type Subscriber<Key> = Box<dyn FnMut(ContainerEvent<'_, Key>) + Send + Sync>;
+
+// Views a service as `Marker`, see `ContainerBuilder::register_as`.
+type MarkerCast<SvcBase, Marker> = Box<dyn Fn(&SvcBase) -> Option<&Marker> + Send + Sync>;
+
+// A `MarkerCast` with its marker type erased, so that marked keys can be moved around generically.
+type ErasedCast = Arc<dyn Any + Send + Sync>;
+
+/// Shape in which `ContainerBuilder::register_fn` stores closures; pass a tuple as `Args` for 
+/// closures taking multiple arguments.
+pub type ServiceFn<Args, R> = Box<dyn Fn(Args) -> R + Send + Sync>;
+
+/// Outcome of `Container::resolve_set`: every service which could be read, and why the others
+/// couldn't.
+pub struct PartialResolution<'a, Key: 'a, SvcBase: ?Sized + 'a> {
+    pub found: Vec<(&'a Key, RwLockReadGuard<'a, Box<SvcBase>>)>,
+    pub missing: Vec<Error<'a, Key>>,
+}
+
+/// Outcome of `Container::explain`: why (or why not) a service can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolutionExplanation {
+    /// Nothing is registered under the service's key.
+    Absent,
+    /// A service is registered, but its lock was poisoned.
+    Poisoned,
+    /// A service is registered, but currently locked for writing.
+    Locked,
+    /// A service is registered, but it's disabled, behind a closed gate or denied by middleware.
+    Disabled,
+    /// A service is registered, but it's not of the requested type.
+    WrongType,
+    /// The service could be read right now.
+    Ok,
+}
+
+/// Per-key lookup counts, as recorded by a container built with `ContainerBuilder::with_metrics`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionStats<Key: Ord> {
+    pub hits: BTreeMap<Key, usize>,
+    pub misses: BTreeMap<Key, usize>,
+}
+
+impl<Key: Ord> Default for ResolutionStats<Key> {
+    fn default() -> Self {
+        ResolutionStats{ hits: BTreeMap::new(), misses: BTreeMap::new() }
+    }
+}
+
+// Per-key bookkeeping besides the service itself.
+#[derive(Default, Clone)]
+struct Meta {
+    tags: BTreeSet<String>,
+    type_name: Option<&'static str>,
+    doc: Option<String>,
+    gate: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+    disabled: bool,
+    priority: i32,
+    init_arg: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+struct Deprecation<Key> {
+    replacement: Key,
+    warn: DeprecationWarning<Key>,
+    warned: Once,
+}
 
-pub struct Container<Key, SvcBase: ?Sized> {
+pub struct Container<Key: Ord, SvcBase: ?Sized> {
     services: BTreeMap<Key, RwLock<Box<SvcBase>>>,
     deadlock_protection: Mutex<()>,
+    normalize: Option<Arc<dyn KeyNormalizer<Key>>>,
+    meta: BTreeMap<Key, Meta>,
+    stats: Option<Mutex<ResolutionStats<Key>>>,
+    type_cache: Option<Mutex<HashMap<TypeId, Key>>>,
+    middleware: Vec<Middleware<Key, SvcBase>>,
+    deprecated: BTreeMap<Key, Deprecation<Key>>,
+    subscribers: Vec<Subscriber<Key>>,
+    // Keys registered as `Marker` along with their `MarkerCast<SvcBase, Marker>`, by `TypeId` 
+    // of `Marker`.
+    marked: HashMap<TypeId, Vec<(Key, ErasedCast)>>,
+    constraints: BTreeMap<Key, TypeConstraint<SvcBase>>,
+    // Keys found during the current stage since the staging builder last registered a service, 
+    // see `ContainerBuilder::with_dependency_tracing`.
+    trace: Option<Arc<Mutex<BTreeSet<Key>>>>,
 }
 
 impl<Key, SvcBase: ?Sized> Container<Key, SvcBase> 
@@ -28,12 +160,143 @@ impl<Key, SvcBase: ?Sized> Container<Key, SvcBase>
 {
     #[doc(hidden)]
     pub fn new() -> Self {
-        Container{ services: BTreeMap::new(), deadlock_protection: Mutex::new(()) }
+        Container{ 
+            services: BTreeMap::new(), 
+            deadlock_protection: Mutex::new(()),
+            normalize: None,
+            meta: BTreeMap::new(),
+            stats: None,
+            type_cache: None,
+            middleware: Vec::new(),
+            deprecated: BTreeMap::new(),
+            subscribers: Vec::new(),
+            marked: HashMap::new(),
+            constraints: BTreeMap::new(),
+            trace: None,
+        }
+    }
+
+    fn emit(&mut self, event: ContainerEvent<'_, Key>) {
+        for subscriber in &mut self.subscribers {
+            subscriber(event.clone());
+        }
+    }
+
+    // Normalizes `key` and follows it to its replacement if it's deprecated, yielding the key 
+    // the service is actually registered under.
+    fn resolved<'k>(&'k self, key: &'k Key) -> Cow<'k, Key> {
+        let key = self.normalized(key);
+        match self.deprecated.get(&*key) {
+            Some(dep) if !self.services.contains_key(&*key) => Cow::Borrowed(&dep.replacement),
+            _ => key,
+        }
+    }
+
+    // Whether the service registered under the resolved `key` is neither disabled nor behind a 
+    // closed gate.
+    fn enabled(&self, key: &Key) -> bool {
+        self.meta.get(key).is_none_or(|meta| !meta.disabled && meta.gate.as_ref().is_none_or(|open| open()))
+    }
+
+    // Whether a lookup of `key` may hand out `svc`, i.e. it's enabled and no middleware denies 
+    // it. Middleware sees the key `svc` is registered under, so that neither differently cased 
+    // nor deprecated keys get past it. Also used by `NamespacedView`, which reports errors for 
+    // relative keys.
+    pub(crate) fn admits(&self, key: &Key, svc: &SvcBase) -> bool {
+        let key = self.resolved(key);
+        self.enabled(&key) && self.middleware.iter().all(|allow| allow(&key, svc))
+    }
+
+    fn admit<'a, G>(&self, key: &'a Key, svc: G) -> Result<G, Error<'a, Key>>
+        where G: Deref<Target = Box<SvcBase>>
+    {
+        if self.admits(key, &**svc) {
+            Ok(svc)
+        } else {
+            Err(Error::NotFound{ key })
+        }
+    }
+
+    // Has to be called whenever a service gets added, since it might make a cached type 
+    // ambiguous. Removed or replaced services are caught by `read_unique` itself.
+    fn invalidate_type_cache(&mut self) {
+        if let Some(ref mut cache) = self.type_cache {
+            cache.get_mut().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+    }
+
+    fn normalized<'k>(&self, key: &'k Key) -> Cow<'k, Key> {
+        match self.normalize {
+            Some(ref normalize) => normalize.normalize(key),
+            None => Cow::Borrowed(key),
+        }
     }
 
+    // Panics unless `svc` is of the type required under `key` via 
+    // `ContainerBuilder::constrain_type`.
+    fn check_constraint(&self, key: &Key, svc: &SvcBase) {
+        if let Some(constraint) = self.constraints.get(key) {
+            assert!(
+                (constraint.is_type)(svc),
+                "service registered under {:?} isn't a `{}`, as required via `constrain_type`", 
+                key, 
+                constraint.type_name,
+            );
+        }
+    }
+
+    /// NOTE: Panics if `key` is an empty or whitespace-only string.
     #[doc(hidden)]
     pub fn register_service(&mut self, key: Key, svc: Box<SvcBase>) -> &mut Self {
+        let key = self.normalized(&key).into_owned();
+        assert!(!is_blank(&key), "service registered under the blank key {:?}", key);
+        self.check_constraint(&key, &*svc);
+        self.emit(ContainerEvent::Registered{ key: &key });
+        if let Some(doc) = self.meta.remove(&key).and_then(|meta| meta.doc) {
+            self.meta.insert(key.clone(), Meta{ doc: Some(doc), ..Meta::default() });
+        }
         self.services.insert(key, RwLock::new(svc));
+        self.invalidate_type_cache();
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn register_tagged(&mut self, key: Key, svc: Box<SvcBase>, tags: BTreeSet<String>) -> &mut Self {
+        let key = self.normalized(&key).into_owned();
+        assert!(!is_blank(&key), "service registered under the blank key {:?}", key);
+        self.check_constraint(&key, &*svc);
+        self.emit(ContainerEvent::Registered{ key: &key });
+        self.services.insert(key.clone(), RwLock::new(svc));
+        let doc = self.meta.remove(&key).and_then(|meta| meta.doc);
+        self.meta.insert(key, Meta{ tags, doc, ..Meta::default() });
+        self.invalidate_type_cache();
+        self
+    }
+
+    // Drops `key` from every list of marked services, handing out its casts by marker type.
+    fn take_marked(&mut self, key: &Key) -> Vec<(TypeId, ErasedCast)> {
+        let mut ret = Vec::new();
+        for (&marker, marked) in &mut self.marked {
+            let (taken, kept): (Vec<_>, Vec<_>) = ::std::mem::take(marked).into_iter()
+                .partition(|(marked, _)| marked == key);
+            *marked = kept;
+            ret.extend(taken.into_iter().map(|(_, cast)| (marker, cast)));
+        }
+        ret
+    }
+
+    // Marks `key` with the casts handed out by `take_marked`.
+    fn put_marked(&mut self, key: &Key, casts: Vec<(TypeId, ErasedCast)>) {
+        for (marker, cast) in casts {
+            self.marked.entry(marker).or_default().push((key.clone(), cast));
+        }
+    }
+
+    // Like `register_service`, but remembers that `svc` is a `Svc` for `type_names`.
+    fn register_typed<Svc: ?Sized>(&mut self, key: Key, svc: Box<SvcBase>) -> &mut Self {
+        let normalized = self.normalized(&key).into_owned();
+        self.register_service(key, svc);
+        self.meta.entry(normalized).or_default().type_name = Some(type_name::<Svc>());
         self
     }
 
@@ -42,7 +305,9 @@ impl<Key, SvcBase: ?Sized> Container<Key, SvcBase>
     where
         Svc: reflect::Service<Key = Key> + Into<Box<SvcBase>>,
     {
-        self.register_service(Svc::key().clone(), svc.into())
+        let key = Svc::key();
+        assert!(!is_blank(key), "`{}` registered under the blank key {:?}, see its `Service::key` impl", type_name::<Svc>(), key);
+        self.register_typed::<Svc>(key.clone(), svc.into())
     }
 
     #[doc(hidden)]
@@ -53,71 +318,373 @@ impl<Key, SvcBase: ?Sized> Container<Key, SvcBase>
         self.register(Svc::default())
     }
 
+    #[doc(hidden)]
+    pub fn rename_service<'a>(&mut self, old: &'a Key, new: Key) -> Result<(), RenameError<'a, Key>> {
+        let new = self.normalized(&new).into_owned();
+        if self.services.contains_key(&new) {
+            return Err(RenameError::AlreadyExists{ key: new });
+        }
+        let old_norm = self.normalized(old).into_owned();
+        match self.services.remove(&old_norm) {
+            Some(svc) => {
+                self.emit(ContainerEvent::Renamed{ old: &old_norm, new: &new });
+                if let Some(meta) = self.meta.remove(&old_norm) {
+                    self.meta.insert(new.clone(), meta);
+                }
+                let marked = self.take_marked(&old_norm);
+                self.put_marked(&new, marked);
+                self.services.insert(new, svc);
+                Ok(())
+            }
+            None => Err(RenameError::NotFound{ key: old }),
+        }
+    }
+
     pub fn services(&self) -> &BTreeMap<Key, RwLock<Box<SvcBase>>> {
         &self.services
     }
+
+    /// Disables or re-enables the service under `key`. Lookups of a disabled service fail with 
+    /// `NotFound`, while the service itself stays registered.
+    pub fn set_enabled(&mut self, key: &Key, enabled: bool) {
+        let key = self.normalized(key).into_owned();
+        if self.services.contains_key(&key) {
+            self.meta.entry(key).or_default().disabled = !enabled;
+            self.invalidate_type_cache();
+        }
+    }
+
+    /// Returns the keys of all services registered via `ContainerBuilder::register_tagged` with 
+    /// `tag` among their tags.
+    pub fn keys_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Key> + 'a {
+        self.meta.iter().filter(move |&(_, meta)| meta.tags.contains(tag)).map(|(key, _)| key)
+    }
+
+    // Whether the service under `key` is tagged with `tag`, for `Query::tagged`.
+    pub(crate) fn has_tag(&self, key: &Key, tag: &str) -> bool {
+        self.meta.get(&*self.normalized(key)).is_some_and(|meta| meta.tags.contains(tag))
+    }
+
+    /// Returns the description attached to `key` via `ContainerBuilder::document`.
+    pub fn doc(&self, key: &Key) -> Option<&str> {
+        self.meta.get(&*self.normalized(key)).and_then(|meta| meta.doc.as_ref()).map(String::as_str)
+    }
+
+    /// Returns the type names of all services whose type was known at registration, i.e. 
+    /// which were registered via `register`, `register_fn`, `register_shared`, `register_as`, 
+    /// `register_value`, `register_pinned` or `get_mut_or_insert_with`.
+    pub fn type_names(&self) -> impl Iterator<Item = (&Key, &'static str)> + '_ {
+        self.meta.iter().filter_map(|(key, meta)| meta.type_name.map(|name| (key, name)))
+    }
+
+    /// Lists every key along with the type name of its service, one `key = type` line each and 
+    /// sorted by key, e.g. for a `--dump-config` flag. Services whose type wasn't known at 
+    /// registration show up as `<untyped>`.
+    pub fn dump(&self) -> String
+        where Key: Display
+    {
+        let mut ret = String::new();
+        for key in self.services.keys() {
+            let type_name = self.meta.get(key).and_then(|meta| meta.type_name).unwrap_or("<untyped>");
+            ret.push_str(&format!("{} = {}\n", key, type_name));
+        }
+        ret
+    }
+
+    /// Hands every service registered as `Marker` via `ContainerBuilder::register_as` to `f`, 
+    /// read-locking each one in turn. Services which can't be read (e.g. disabled ones), or 
+    /// which were replaced by one of another type since, are skipped.
+    pub fn for_each_marked<Marker, F>(&self, mut f: F)
+        where Marker: ?Sized + Any, F: FnMut(&Key, &Marker)
+    {
+        let marked = self.marked.get(&TypeId::of::<Marker>()).into_iter().flatten()
+            .filter_map(|(key, cast)| Some((key, cast.downcast_ref::<MarkerCast<SvcBase, Marker>>()?)));
+        for (key, cast) in marked {
+            if let Some(svc) = self.services.get(key).and_then(|svc| svc.read().ok()) {
+                if !self.admits(key, &**svc) {
+                    continue;
+                }
+                if let Some(marker) = cast(&**svc) {
+                    f(key, marker);
+                }
+            }
+        }
+    }
+
+    /// Iterates over every service of type `Svc` by descending priority (see 
+    /// `ContainerBuilder::register_with_priority`) and then by key, read-locking each one in 
+    /// turn. Services which can't be read (e.g. poisoned or disabled ones) are skipped.
+    pub fn iter_of<'a, Svc>(&'a self) -> impl Iterator<Item = (&'a Key, ReadGuard<'a, Svc, SvcBase>)> + 'a
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        let mut services: Vec<_> = self.services.iter().collect();
+        services.sort_by_key(|&(key, _)| Reverse(self.meta.get(key).map_or(0, |meta| meta.priority)));
+        services.into_iter().filter_map(move |(key, svc)| {
+            let base = svc.read().ok().filter(|base| self.admits(key, &***base))?;
+            ReadGuard::wrap(base).ok().map(|svc| (key, svc))
+        })
+    }
+
+    /// Iterates over the services whose keys lie in `range`, read-locking each one in turn. 
+    /// Services which can't be read (e.g. poisoned ones) are skipped.
+    pub fn range<'a, R>(&'a self, range: R) -> impl Iterator<Item = (&'a Key, RwLockReadGuard<'a, Box<SvcBase>>)> + 'a
+        where R: RangeBounds<Key>
+    {
+        let start = range.start_bound().map(|key| self.normalized(key).into_owned());
+        let end = range.end_bound().map(|key| self.normalized(key).into_owned());
+        self.services.range((start, end))
+            .filter_map(move |(key, _)| self.read_service_base(key).ok().map(|svc| (key, svc)))
+    }
+
+    /// Iterates over all services mutably, handing out a clone of each key so it can outlive the 
+    /// borrow of the container.
+    pub fn iter_mut_owned_keys<'a>(&'a mut self) -> impl Iterator<Item = (Key, &'a mut Box<SvcBase>)> + 'a {
+        self.services.iter_mut()
+            .map(|(key, svc)| (key.clone(), svc.get_mut().unwrap_or_else(PoisonError::into_inner)))
+    }
+
+    /// Whether a lookup of `key` finds a service, following deprecated keys to their replacement. 
+    /// Disabled services and those behind a closed gate count as missing. Neither counts towards 
+    /// the metrics nor consults the middleware.
+    pub fn contains(&self, key: &Key) -> bool {
+        let key = self.resolved(key);
+        self.services.contains_key(&*key) && self.enabled(&key)
+    }
+
+    /// Whether a service is registered under each of `keys`.
+    pub fn all_registered(&self, keys: &[Key]) -> bool {
+        keys.iter().all(|key| self.services.contains_key(&*self.normalized(key)))
+    }
+
+    /// Returns those of `keys` which have no service registered under them.
+    pub fn missing_of<'a>(&self, keys: &'a [Key]) -> Vec<&'a Key> {
+        keys.iter().filter(|key| !self.services.contains_key(&*self.normalized(key))).collect()
+    }
+
+    /// Splits the registered keys into `(healthy, poisoned)` ones, in a single pass.
+    pub fn partition(&self) -> (Vec<&Key>, Vec<&Key>) {
+        let mut ret = (Vec::new(), Vec::new());
+        for (key, svc) in &self.services {
+            if svc.is_poisoned() {
+                ret.1.push(key);
+            } else {
+                ret.0.push(key);
+            }
+        }
+        ret
+    }
+
+    /// Checks the container for inconsistencies, e.g. descriptions of keys which never got a 
+    /// service or deprecations pointing nowhere.
+    pub fn audit(&self) -> AuditReport<'_, Key> {
+        AuditReport{
+            orphaned: self.meta.keys().filter(|key| !self.services.contains_key(key)).collect(),
+            dangling_deprecations: self.deprecated.iter()
+                .filter(|&(key, dep)| !self.services.contains_key(key) && !self.services.contains_key(&dep.replacement))
+                .map(|(key, _)| key)
+                .collect(),
+            poisoned: self.partition().1,
+        }
+    }
+
+    /// Hashes the keys, tags and concrete service types of the container, e.g. for keying a
+    /// cache on the configuration. Containers of the same shape give the same fingerprint.
+    ///
+    /// Read-locks every service in turn, so it blocks while any of them is locked for writing.
+    ///
+    /// NOTE: Neither `TypeId`s nor `DefaultHasher` are stable across builds or toolchains, so 
+    /// fingerprints are only comparable within one build of the same program, e.g. for an 
+    /// in-memory cache, not for one persisted to disk.
+    pub fn fingerprint(&self) -> u64
+        where Key: Hash, SvcBase: downcast::Any
+    {
+        let mut hasher = DefaultHasher::new();
+        self.shape().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether both containers have the same keys, tags and concrete service types, regardless 
+    /// of the state of the services themselves. Locks like `fingerprint`.
+    pub fn same_shape<U>(&self, other: &Container<Key, U>) -> bool
+        where SvcBase: downcast::Any, U: ?Sized + downcast::Any
+    {
+        self.shape() == other.shape()
+    }
+
+    fn shape(&self) -> Vec<(&Key, TypeId, Option<&BTreeSet<String>>)>
+        where SvcBase: downcast::Any
+    {
+        self.services.iter()
+            .map(|(key, svc)| {
+                let svc = svc.read().unwrap_or_else(PoisonError::into_inner);
+                let tags = self.meta.get(key).map(|meta| &meta.tags).filter(|tags| !tags.is_empty());
+                (key, downcast::Any::_get_type_id(&**svc), tags)
+            })
+            .collect()
+    }
+
+    /// Consumes the container, handing out its services in a plain `HashMap`.
+    ///
+    /// Meant for interop with code which doesn't depend on this crate. Poisoned services are 
+    /// returned regardless, since nobody can be holding their locks anymore.
+    pub fn into_service_map(self) -> HashMap<Key, Box<SvcBase>>
+        where Key: Hash
+    {
+        self.services.into_iter()
+            .map(|(key, svc)| (key, svc.into_inner().unwrap_or_else(PoisonError::into_inner)))
+            .collect()
+    }
+    
+    /// Returns the lookup counts recorded so far, or nothing if metrics weren't enabled.
+    pub fn stats(&self) -> ResolutionStats<Key> {
+        match self.stats {
+            Some(ref stats) => stats.lock().unwrap_or_else(PoisonError::into_inner).clone(),
+            None => ResolutionStats::default(),
+        }
+    }
+
+    /// Returns how often each registered service was found so far, including the ones which 
+    /// never were, e.g. to spot unused services. All counts are zero unless metrics are enabled.
+    pub fn access_counts(&self) -> Vec<(&Key, usize)> {
+        let stats = self.stats();
+        self.services.keys().map(|key| (key, stats.hits.get(key).cloned().unwrap_or(0))).collect()
+    }
+
+    /// Consumes the container, converting every service with `f` into a container over another 
+    /// base type. Keys, tags and settings carry over, while recorded metrics start from scratch.
+    pub fn map_services<U, F>(self, mut f: F) -> Container<Key, U>
+        where U: ?Sized + Any, F: FnMut(&Key, Box<SvcBase>) -> Box<U>
+    {
+        let services = self.services.into_iter()
+            .map(|(key, svc)| {
+                let svc = f(&key, svc.into_inner().unwrap_or_else(PoisonError::into_inner));
+                (key, RwLock::new(svc))
+            })
+            .collect();
+        Container{ 
+            services, 
+            deadlock_protection: Mutex::new(()),
+            normalize: self.normalize, 
+            meta: self.meta.into_iter().map(|(key, meta)| (key, Meta{ type_name: None, ..meta })).collect(), 
+            stats: self.stats.as_ref().map(|_| Mutex::new(ResolutionStats::default())),
+            type_cache: self.type_cache.as_ref().map(|_| Mutex::new(HashMap::new())),
+            middleware: Vec::new(),
+            deprecated: self.deprecated,
+            subscribers: self.subscribers,
+            marked: HashMap::new(),
+            constraints: BTreeMap::new(),
+            trace: None,
+        }
+    }
+
+    /// Consumes the container, dropping the services listed in `order` first (in that order) 
+    /// and the remaining ones afterwards. Every service gets its `OnShutdown` hook invoked right
+    /// before it is dropped.
+    pub fn shutdown(mut self, order: &[Key])
+        where SvcBase: OnShutdown
+    {
+        fn finish<SvcBase: ?Sized + OnShutdown>(svc: RwLock<Box<SvcBase>>) {
+            svc.into_inner().unwrap_or_else(PoisonError::into_inner).on_shutdown();
+        }
+
+        for key in order {
+            let svc = self.services.remove(&*self.normalized(key));
+            if let Some(svc) = svc {
+                finish(svc);
+            }
+        }
+        for (_, svc) in self.services {
+            finish(svc);
+        }
+    }
     
+    // Like `resolved`, but runs the deprecation warning the first time an alias is followed.
+    fn resolved_warned<'k>(&'k self, key: &'k Key) -> Cow<'k, Key> {
+        let key = self.normalized(key);
+        match self.deprecated.get(&*key) {
+            Some(dep) if !self.services.contains_key(&*key) => {
+                dep.warned.call_once(|| (dep.warn)(&key, &dep.replacement));
+                Cow::Borrowed(&dep.replacement)
+            }
+            _ => key,
+        }
+    }
+
+    // Fails with `NotFound` unless a service is registered under the `resolved` key and a lookup 
+    // of `key` may hand it out, see `admits`. For the `&mut self` methods, which bypass 
+    // `get_service`.
+    fn check_admitted<'a>(&self, key: &'a Key, resolved: &Key) -> Result<(), Error<'a, Key>> {
+        match self.services.get(resolved) {
+            Some(svc) if self.admits(key, &**errors::or_err(key, svc.read())?) => Ok(()),
+            _ => Err(Error::NotFound{ key }),
+        }
+    }
+
     pub fn get_service(&self, key: &Key) -> Option<&RwLock<Box<SvcBase>>> {
-        self.services.get(key)
+        let key = self.resolved_warned(key);
+        let ret = self.services.get(&*key);
+        if let Some(ref stats) = self.stats {
+            let mut stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+            let counts = if ret.is_some() { &mut stats.hits } else { &mut stats.misses };
+            *counts.entry(key.clone().into_owned()).or_insert(0) += 1;
+        }
+        if let (Some(trace), Some(_)) = (&self.trace, ret) {
+            trace.lock().unwrap_or_else(PoisonError::into_inner).insert(key.into_owned());
+        }
+        ret
     }
 
     pub fn read_service_base<'a>(
         &'a self, 
         key: &'a Key
-    ) -> Result<RwLockReadGuard<Box<SvcBase>>, Error<'a, Key>> {
+    ) -> Result<RwLockReadGuard<'a, Box<SvcBase>>, Error<'a, Key>> {
         match self.get_service(key) {
-            Some(service) => errors::or_err(key, service.read()),
-            None => Err(Error::NotFound{ key: key })
+            Some(service) => self.admit(key, errors::or_err(key, service.read())?),
+            None => Err(Error::NotFound{ key })
         }
     }
 
     pub fn write_service_base<'a>(
         &'a self, 
         key: &'a Key
-    ) -> Result<RwLockWriteGuard<Box<SvcBase>>, Error<'a, Key>> {
+    ) -> Result<RwLockWriteGuard<'a, Box<SvcBase>>, Error<'a, Key>> {
         match self.get_service(key) {
-            Some(service) => errors::or_err(key, service.write()),
-            None => Err(Error::NotFound{ key: key })
+            Some(service) => self.admit(key, errors::or_err(key, service.write())?),
+            None => Err(Error::NotFound{ key })
         }
     }
 
     pub fn read_service<'a, Svc>(
         &'a self, 
         key: &'a Key
-    ) -> Result<ReadGuard<Svc, SvcBase>, Error<'a, Key>>
+    ) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, Key>>
         where Svc: Any, SvcBase: Downcast<Svc>
     {
-        let base = try!{self.read_service_base(key)};
-        if !base.is_type() {
-            return Err(Error::MismatchedType{ 
-                key: key, 
-                expected: type_name::<Svc>(),
-                found: type_name::<Svc>(),
-            })
-        };
-        Ok(ReadGuard::wrap(base).ok().unwrap())
+        let base = self.read_service_base(key)?;
+        ReadGuard::wrap(base).map_err(|_| Error::MismatchedType{ 
+            key, 
+            expected: type_name::<Svc>(),
+            found: type_name::<Svc>(),
+        })
     }
 
     pub fn write_service<'a, Svc>(
         &'a self, 
         key: &'a Key
-    ) -> Result<WriteGuard<Svc, SvcBase>, Error<'a, Key>>
+    ) -> Result<WriteGuard<'a, Svc, SvcBase>, Error<'a, Key>>
         where Svc: Any, SvcBase: Downcast<Svc>
     {
-        let base = try!{self.write_service_base(key)};
-        if !base.is_type() {
-            return Err(Error::MismatchedType{ 
-                key: key, 
-                expected: type_name::<Svc>(),
-                found: type_name::<Svc>(),
-            })
-        };
-        Ok(WriteGuard::wrap(base).ok().unwrap())
+        let base = self.write_service_base(key)?;
+        WriteGuard::wrap(base).map_err(|_| Error::MismatchedType{ 
+            key, 
+            expected: type_name::<Svc>(),
+            found: type_name::<Svc>(),
+        })
     }
 
     pub fn read<'a, Svc>(
         &'a self
-    ) -> Result<ReadGuard<Svc, SvcBase>, Error<'a, Key>>
+    ) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, Key>>
         where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
     {
         self.read_service(Svc::key())
@@ -125,139 +692,2832 @@ impl<Key, SvcBase: ?Sized> Container<Key, SvcBase>
 
     pub fn write<'a, Svc>(
         &'a self
-    ) -> Result<WriteGuard<Svc, SvcBase>, Error<'a, Key>>
+    ) -> Result<WriteGuard<'a, Svc, SvcBase>, Error<'a, Key>>
         where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
     {
         self.write_service(Svc::key())
     }
 
-    pub fn try_read_service_base<'a>(
+    /// Reads the first of `keys` which resolves to a `Svc`, e.g. to implement a precedence chain 
+    /// like `["user.theme", "workspace.theme", "default.theme"]`.
+    ///
+    /// Fails with the error for the last key if none of them work out.
+    ///
+    /// NOTE: Panics if `keys` is empty, since there would be no key to report.
+    pub fn read_first<'a, Svc>(
         &'a self, 
-        key: &'a Key
-    ) -> Result<RwLockReadGuard<Box<SvcBase>>, Error<'a, Key>> {
-        match self.get_service(key) {
-            Some(service) => errors::or_err(key, service.read()),
-            None => Err(Error::NotFound{ key: key })
+        keys: &'a [Key]
+    ) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, Key>>
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        let mut last = None;
+        for key in keys {
+            match self.read_service(key) {
+                Ok(svc) => return Ok(svc),
+                Err(err) => last = Some(err),
+            }
         }
+        Err(last.expect("`read_first` needs at least one key"))
     }
 
-    pub fn try_write_service_base<'a>(
-        &'a self, 
-        key: &'a Key
-    ) -> Result<RwLockWriteGuard<Box<SvcBase>>, Error<'a, Key>> {
-        match self.get_service(key) {
-            Some(service) => errors::or_err(key, service.write()),
-            None => Err(Error::NotFound{ key: key })
+    /// Returns `Svc` mutably, registering `f()` under `key` first if nothing is registered there 
+    /// yet. Like any other lookup, fails with `NotFound` if the service there is disabled.
+    ///
+    /// No locking is involved, since `&mut self` already guarantees exclusive access.
+    pub fn get_mut_or_insert_with<'a, Svc, F>(
+        &'a mut self, 
+        key: &'a Key, 
+        f: F
+    ) -> Result<&'a mut Svc, Error<'a, Key>>
+        where Svc: Any + Into<Box<SvcBase>>, SvcBase: Downcast<Svc>, F: FnOnce() -> Svc
+    {
+        let resolved = self.resolved_warned(key).into_owned();
+        if !self.services.contains_key(&resolved) {
+            self.register_typed::<Svc>(resolved.clone(), f().into());
         }
+        self.check_admitted(key, &resolved)?;
+        downcast_service_mut(key, self.services.get_mut(&resolved).unwrap())
     }
 
-    pub fn try_read_service<'a, Svc>(
-        &'a self, 
-        key: &'a Key
-    ) -> Result<ReadGuard<Svc, SvcBase>, Error<'a, Key>>
+    /// Like `get_mut_or_insert_with`, but under `Svc`'s own key.
+    pub fn get_or_create<'a, Svc, F>(&'a mut self, f: F) -> Result<&'a mut Svc, Error<'a, Key>>
+        where Svc: reflect::Service<Key = Key> + Into<Box<SvcBase>>, SvcBase: Downcast<Svc>, F: FnOnce() -> Svc
+    {
+        self.get_mut_or_insert_with(Svc::key(), f)
+    }
+
+    /// Borrows the two services `A` and `B` mutably at the same time, without locking.
+    ///
+    /// NOTE: Panics if `A` and `B` share the same key.
+    pub fn get_disjoint_mut<'a, A, B>(&'a mut self) -> Result<(&'a mut A, &'a mut B), Error<'a, Key>>
+    where
+        A: reflect::Service<Key = Key>,
+        B: reflect::Service<Key = Key>,
+        SvcBase: Downcast<A> + Downcast<B>,
+    {
+        let (key_a, key_b) = (A::key(), B::key());
+        let norm_a = self.resolved_warned(key_a).into_owned();
+        let norm_b = self.resolved_warned(key_b).into_owned();
+        assert!(norm_a != norm_b, "`get_disjoint_mut` called twice with key {:?}", key_a);
+        self.check_admitted(key_a, &norm_a)?;
+        self.check_admitted(key_b, &norm_b)?;
+
+        let (mut a, mut b) = (None, None);
+        for (key, service) in self.services.iter_mut() {
+            if *key == norm_a {
+                a = Some(service);
+            } else if *key == norm_b {
+                b = Some(service);
+            }
+        }
+        let a = downcast_service_mut(key_a, a.ok_or(Error::NotFound{ key: key_a })?)?;
+        let b = downcast_service_mut(key_b, b.ok_or(Error::NotFound{ key: key_b })?)?;
+        Ok((a, b))
+    }
+
+    /// Removes the service under `key` from the container and hands it out as `Svc`. Fails with 
+    /// `MismatchedType` if it is of another type, leaving it registered.
+    pub fn take_service<'a, Svc>(&mut self, key: &'a Key) -> Result<Box<Svc>, Error<'a, Key>>
         where Svc: Any, SvcBase: Downcast<Svc>
     {
-        let base = try!{self.try_read_service_base(key)};
-        if !base.is_type() {
-            return Err(Error::MismatchedType{ 
-                key: key, 
-                expected: type_name::<Svc>(),
-                found: type_name::<Svc>(),
-            })
+        let normalized = self.normalized(key).into_owned();
+        let svc = match self.services.remove(&normalized) {
+            Some(svc) => svc.into_inner().unwrap_or_else(PoisonError::into_inner),
+            None => return Err(Error::NotFound{ key }),
+        };
+        match Downcast::<Svc>::downcast(svc) {
+            Ok(svc) => {
+                self.emit(ContainerEvent::Removed{ key: &normalized });
+                self.meta.remove(&normalized);
+                self.take_marked(&normalized);
+                Ok(svc)
+            }
+            Err(svc) => {
+                self.services.insert(normalized, RwLock::new(svc));
+                Err(Error::MismatchedType{ 
+                    key, 
+                    expected: type_name::<Svc>(),
+                    found: type_name::<SvcBase>(),
+                })
+            }
+        }
+    }
+
+    /// Invokes the closure registered under `key` via `ContainerBuilder::register_fn`.
+    ///
+    /// Fails with `MismatchedType` if it was registered with a different signature.
+    pub fn call<'a, Args, R>(&'a self, key: &'a Key, args: Args) -> Result<R, Error<'a, Key>>
+        where Args: Any, R: Any, SvcBase: Downcast<ServiceFn<Args, R>>
+    {
+        let f = self.read_service::<ServiceFn<Args, R>>(key)?;
+        Ok(f(args))
+    }
+
+    /// Diagnoses whether `Svc` could be read right now, without blocking. Neither counts towards 
+    /// the metrics nor runs deprecation warnings.
+    pub fn explain<Svc>(&self) -> ResolutionExplanation
+        where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
+    {
+        let service = match self.services.get(&*self.resolved(Svc::key())) {
+            Some(service) => service,
+            None => return ResolutionExplanation::Absent,
         };
-        Ok(ReadGuard::wrap(base).ok().unwrap())
+        match service.try_read() {
+            Ok(ref base) if !self.admits(Svc::key(), &***base) => ResolutionExplanation::Disabled,
+            Ok(ref base) if !base.is_type() => ResolutionExplanation::WrongType,
+            Ok(_) => ResolutionExplanation::Ok,
+            Err(TryLockError::Poisoned(_)) => ResolutionExplanation::Poisoned,
+            Err(TryLockError::WouldBlock) => ResolutionExplanation::Locked,
+        }
     }
 
-    pub fn try_write_service<'a, Svc>(
-        &'a self, 
-        key: &'a Key
-    ) -> Result<WriteGuard<Svc, SvcBase>, Error<'a, Key>>
+    // Whether a service behind a gate might be a `Svc`. Gates can open at any time, so 
+    // `read_unique` doesn't cache types which such a service might make ambiguous.
+    fn gated_of<Svc>(&self) -> bool
         where Svc: Any, SvcBase: Downcast<Svc>
     {
-        let base = try!{self.try_write_service_base(key)};
-        if !base.is_type() {
-            return Err(Error::MismatchedType{ 
-                key: key, 
-                expected: type_name::<Svc>(),
-                found: type_name::<Svc>(),
+        self.meta.iter().filter(|(_, meta)| meta.gate.is_some()).any(|(key, _)| {
+            self.services.get(key).is_some_and(|svc| {
+                svc.try_read().map_or(true, |base| Downcast::<Svc>::is_type(&**base))
             })
-        };
-        Ok(WriteGuard::wrap(base).ok().unwrap())
+        })
     }
 
-    pub fn try_read<'a, Svc>(
-        &'a self
-    ) -> Result<ReadGuard<Svc, SvcBase>, Error<'a, Key>>
+    /// Reads the one service of type `Svc`, whatever key it's registered under.
+    ///
+    /// Fails with `NotFound` if there is none and with `Ambiguous` if there are several, both 
+    /// reported for `Svc::key()`. See `ContainerBuilder::with_type_cache` for skipping the scan 
+    /// over all services.
+    pub fn read_unique<'a, Svc>(&'a self) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, Key>>
         where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
     {
-        self.try_read_service(Svc::key())
+        if let Some(ref cache) = self.type_cache {
+            let cached = cache.lock().unwrap_or_else(PoisonError::into_inner).get(&TypeId::of::<Svc>()).cloned();
+            let svc = cached
+                .and_then(|key| self.services.get_key_value(&key))
+                .and_then(|(key, svc)| svc.read().ok().filter(|base| self.admits(key, &***base)))
+                .and_then(|base| ReadGuard::wrap(base).ok());
+            if let Some(svc) = svc {
+                return Ok(svc);
+            }
+        }
+
+        let mut found = self.iter_of::<Svc>();
+        match (found.next(), found.next()) {
+            (Some((key, svc)), None) => {
+                if let (Some(cache), false) = (&self.type_cache, self.gated_of::<Svc>()) {
+                    cache.lock().unwrap_or_else(PoisonError::into_inner).insert(TypeId::of::<Svc>(), key.clone());
+                }
+                Ok(svc)
+            }
+            (Some(_), Some(_)) => Err(Error::Ambiguous{ key: Svc::key() }),
+            (None, _) => Err(Error::NotFound{ key: Svc::key() }),
+        }
     }
 
-    pub fn try_write<'a, Svc>(
+    /// Like `read`, but panics with `msg` and the error (including the key) on failure. Meant for 
+    /// services whose absence is a programming error.
+    pub fn expect_read<'a, Svc>(&'a self, msg: &str) -> ReadGuard<'a, Svc, SvcBase>
+        where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
+    {
+        match self.read::<Svc>() {
+            Ok(svc) => svc,
+            Err(err) => panic!("{}: {}", msg, err),
+        }
+    }
+
+    /// Like `read`, but also hands out the `TypeId` of the service for uniform logging.
+    pub fn read_with_type<'a, Svc>(
         &'a self
-    ) -> Result<WriteGuard<Svc, SvcBase>, Error<'a, Key>>
+    ) -> Result<(ReadGuard<'a, Svc, SvcBase>, TypeId), Error<'a, Key>>
         where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
     {
-        self.try_write_service(Svc::key())
+        Ok((self.read::<Svc>()?, TypeId::of::<Svc>()))
     }
 
-    pub fn resolve<'a, M>(&'a self) -> Result<M::Ret, Error<Key>>
-        where M: Method<'a, Key, SvcBase>
+    /// Reads the nested container registered under `key`.
+    pub fn sub<'a>(&'a self, key: &'a Key) -> Result<ReadGuard<'a, Self, SvcBase>, Error<'a, Key>>
+        where SvcBase: Downcast<Self>
     {
-        let _ = self.deadlock_protection.lock();
-        M::resolve_unprotected(self)
+        self.read_service(key)
     }
-    pub fn try_resolve<'a, M>(&'a self) -> Result<M::Ret, Error<Key>>
-        where M: Method<'a, Key, SvcBase>
+
+    /// Walks nested containers along `path` and hands the service at its end to `f`, e.g. 
+    /// `["audio", "mixer"]` reads `mixer` from the container registered as `audio`.
+    ///
+    /// NOTE: Panics if `path` is empty.
+    pub fn with_path<'k, Svc, R, F>(&self, path: &'k [Key], f: F) -> Result<R, Error<'k, Key>>
+        where Svc: Any, SvcBase: Downcast<Svc> + Downcast<Self>, F: FnOnce(&Svc) -> R
     {
-        let _ = self.deadlock_protection.lock();
-        M::try_resolve_unprotected(self)
+        let (key, rest) = path.split_first().expect("`with_path` needs at least one key");
+        let base = match self.get_service(key) {
+            Some(service) => self.admit(key, errors::or_err(key, service.read())?)?,
+            None => return Err(Error::NotFound{ key }),
+        };
+        if rest.is_empty() {
+            match Downcast::<Svc>::downcast_ref(&**base) {
+                Some(svc) => Ok(f(svc)),
+                None => Err(Error::MismatchedType{ 
+                    key, 
+                    expected: type_name::<Svc>(),
+                    found: type_name::<Svc>(),
+                }),
+            }
+        } else {
+            match Downcast::<Self>::downcast_ref(&**base) {
+                Some(sub) => sub.with_path(rest, f),
+                None => Err(Error::MismatchedType{ 
+                    key, 
+                    expected: type_name::<Self>(),
+                    found: type_name::<Self>(),
+                }),
+            }
+        }
     }
-}
 
-// ++++++++++++++++++++ ContainerBuilder ++++++++++++++++++++
+    /// Locks `Svc` for writing and hands it to `f`, returning whatever `f` returns.
+    pub fn peek_then<'a, Svc, R, F>(&'a self, f: F) -> Result<R, Error<'a, Key>>
+        where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>, F: FnOnce(&mut Svc) -> R
+    {
+        let mut svc = self.write::<Svc>()?;
+        Ok(f(&mut svc))
+    }
 
-pub struct ContainerBuilder<Key, SvcBase: ?Sized> {
-    cont: Container<Key, SvcBase>
-}
+    /// Read-locks the service under `key` and hands it to `f` as `&dyn Any`, for tooling which 
+    /// doesn't know about `SvcBase`.
+    pub fn read_any<'a, R, F>(&'a self, key: &'a Key, f: F) -> Result<R, Error<'a, Key>>
+        where SvcBase: AsAny, F: FnOnce(&dyn Any) -> R
+    {
+        let svc = self.read_service_base(key)?;
+        Ok(f((**svc).as_any()))
+    }
 
-impl<Key, SvcBase: ?Sized> ContainerBuilder<Key, SvcBase>
-    where Key: reflect::Key, SvcBase: Any
-{
-    pub fn new() -> Self {
-        ContainerBuilder{ cont: Container::new() }
+    /// Returns the address of the service under `key` along with the `TypeId` of its concrete 
+    /// type, e.g. for handing it across an FFI boundary.
+    ///
+    /// The pointer stays valid as long as the service is neither replaced nor removed, which 
+    /// `&self` can't prevent: `write_service_base` can swap it out. Dereferencing it is only 
+    /// sound as the type the `TypeId` names, and only while nobody holds a write lock on it.
+    pub fn raw_service<'a>(&'a self, key: &'a Key) -> Result<(*const (), TypeId), Error<'a, Key>>
+        where SvcBase: downcast::Any
+    {
+        let svc = self.read_service_base(key)?;
+        Ok((&**svc as *const SvcBase as *const (), downcast::Any::_get_type_id(&**svc)))
     }
 
-    pub fn register_service(&mut self, key: Key, svc: Box<SvcBase>) -> &mut Self {
-        self.cont.register_service(key, svc);
-        self
+    /// Returns the first of `candidates` which is the concrete type of the service under `key`, 
+    /// e.g. to dispatch on which of several types it is.
+    pub fn matching_type<'a>(&'a self, key: &'a Key, candidates: &[TypeId]) -> Result<Option<TypeId>, Error<'a, Key>>
+        where SvcBase: downcast::Any
+    {
+        let svc = self.read_service_base(key)?;
+        let type_id = downcast::Any::_get_type_id(&**svc);
+        Ok(candidates.iter().cloned().find(|&candidate| candidate == type_id))
     }
 
-    /// NOTE: The `Box<Svc>: Into<Box<Base>>`-clause is needed due to rusts lack of 
-    /// HKT or a `Coercible`-trait (to name two solutions).
-    pub fn register<Svc>(&mut self, svc: Svc) -> &mut Self
-    where
-        Svc: reflect::Service<Key = Key> + Into<Box<SvcBase>>,
+    /// Clones the `Arc<T>` registered under `key`, e.g. via `ContainerBuilder::register_shared`. 
+    ///
+    /// Unlike guards, the handle doesn't borrow the container, so it can be held across 
+    /// `.await`s. Share the container itself between tasks by putting it into an `Arc`.
+    pub fn shared<'a, T>(&'a self, key: &'a Key) -> Result<Arc<T>, Error<'a, Key>>
+        where T: ?Sized + Any, SvcBase: Downcast<Arc<T>>
     {
-        self.cont.register::<Svc>(svc);
-        self
+        Ok(self.read_service::<Arc<T>>(key)?.clone())
     }
 
-    pub fn register_default<Svc>(&mut self) -> &mut Self
-    where
-        Svc: Default + reflect::Service<Key = Key> + Into<Box<SvcBase>>,
+    /// Clones the `Rc<RefCell<Svc>>` registered under `Svc`'s key, for single-threaded 
+    /// containers whose services are shared and mutated through `RefCell`s.
+    pub fn shared_cell<'a, Svc>(&'a self) -> Result<Rc<RefCell<Svc>>, Error<'a, Key>>
+        where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Rc<RefCell<Svc>>>
     {
-        self.cont.register_default::<Svc>();
-        self
+        Ok(self.read_service::<Rc<RefCell<Svc>>>(Svc::key())?.clone())
     }
 
-    pub fn build(self) -> Container<Key, SvcBase> {
-        self.cont
+    /// Returns a copy of the configuration value registered under `key`, see 
+    /// `ContainerBuilder::register_value`.
+    pub fn value<'a, T>(&'a self, key: &'a Key) -> Result<T, Error<'a, Key>>
+        where T: Any + Clone, SvcBase: Downcast<T>
+    {
+        Ok(self.read_service::<T>(key)?.clone())
     }
-}
 
-impl<Key, SvcBase: ?Sized> Default for ContainerBuilder<Key, SvcBase> 
-    where Key: reflect::Key, SvcBase: Any
-{
-    fn default() -> Self { Self::new() }
+    /// Returns a copy of `Svc`, or `Svc::default()` if it can't be read, e.g. for optional 
+    /// tunables.
+    pub fn read_or_default<Svc>(&self) -> Svc
+        where Svc: reflect::Service<Key = Key> + Default + Clone, SvcBase: Downcast<Svc>
+    {
+        self.read::<Svc>().map(|svc| svc.clone()).unwrap_or_default()
+    }
+
+    /// Read-locks `Svc` and hands it to `f` as a `Cow`, so `f` only pays for a clone if it 
+    /// actually needs to modify its copy. The registered instance stays untouched either way.
+    pub fn read_cow<'a, Svc, R, F>(&'a self, f: F) -> Result<R, Error<'a, Key>>
+        where Svc: reflect::Service<Key = Key> + Clone, SvcBase: Downcast<Svc>, F: FnOnce(Cow<Svc>) -> R
+    {
+        let svc = self.read::<Svc>()?;
+        Ok(f(Cow::Borrowed(&*svc)))
+    }
+
+    /// Read-locks the service under `key`, registered via `ContainerBuilder::register_pinned`, 
+    /// and hands it to `f` without ever moving it.
+    pub fn read_pinned<'a, T, R, F>(&'a self, key: &'a Key, f: F) -> Result<R, Error<'a, Key>>
+        where T: ?Sized + Any, SvcBase: Downcast<Pin<Box<T>>>, F: FnOnce(Pin<&T>) -> R
+    {
+        let svc = self.read_service::<Pin<Box<T>>>(key)?;
+        Ok(f(svc.as_ref()))
+    }
+
+    /// Write-locks the service under `key`, registered via `ContainerBuilder::register_pinned`, 
+    /// and hands it to `f` without ever moving it.
+    pub fn write_pinned<'a, T, R, F>(&'a self, key: &'a Key, f: F) -> Result<R, Error<'a, Key>>
+        where T: ?Sized + Any, SvcBase: Downcast<Pin<Box<T>>>, F: FnOnce(Pin<&mut T>) -> R
+    {
+        let mut svc = self.write_service::<Pin<Box<T>>>(key)?;
+        Ok(f(svc.as_mut()))
+    }
+
+    pub fn try_read_service_base<'a>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<RwLockReadGuard<'a, Box<SvcBase>>, Error<'a, Key>> {
+        match self.get_service(key) {
+            Some(service) => self.admit(key, errors::or_err(key, service.read())?),
+            None => Err(Error::NotFound{ key })
+        }
+    }
+
+    pub fn try_write_service_base<'a>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<RwLockWriteGuard<'a, Box<SvcBase>>, Error<'a, Key>> {
+        match self.get_service(key) {
+            Some(service) => self.admit(key, errors::or_err(key, service.write())?),
+            None => Err(Error::NotFound{ key })
+        }
+    }
+
+    pub fn try_read_service<'a, Svc>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, Key>>
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        let base = self.try_read_service_base(key)?;
+        ReadGuard::wrap(base).map_err(|_| Error::MismatchedType{ 
+            key, 
+            expected: type_name::<Svc>(),
+            found: type_name::<Svc>(),
+        })
+    }
+
+    pub fn try_write_service<'a, Svc>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<WriteGuard<'a, Svc, SvcBase>, Error<'a, Key>>
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        let base = self.try_write_service_base(key)?;
+        WriteGuard::wrap(base).map_err(|_| Error::MismatchedType{ 
+            key, 
+            expected: type_name::<Svc>(),
+            found: type_name::<Svc>(),
+        })
+    }
+
+    pub fn try_read<'a, Svc>(
+        &'a self
+    ) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, Key>>
+        where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
+    {
+        self.try_read_service(Svc::key())
+    }
+
+    pub fn try_write<'a, Svc>(
+        &'a self
+    ) -> Result<WriteGuard<'a, Svc, SvcBase>, Error<'a, Key>>
+        where Svc: reflect::Service<Key = Key>, SvcBase: Downcast<Svc>
+    {
+        self.try_write_service(Svc::key())
+    }
+
+    /// Reads every service in `keys`, collecting failures instead of stopping at the first one.
+    pub fn resolve_set<'a, I>(&'a self, keys: I) -> PartialResolution<'a, Key, SvcBase>
+        where I: IntoIterator<Item = &'a Key>
+    {
+        let _guard = self.deadlock_protection.lock();
+        let mut ret = PartialResolution{ found: Vec::new(), missing: Vec::new() };
+        for key in keys {
+            match self.read_service_base(key) {
+                Ok(svc) => ret.found.push((key, svc)),
+                Err(err) => ret.missing.push(err),
+            }
+        }
+        ret
+    }
+
+    pub fn resolve<'a, M>(&'a self) -> Result<M::Ret, Error<'a, Key>>
+        where M: Method<'a, Key, SvcBase>
+    {
+        let _guard = self.deadlock_protection.lock();
+        M::resolve_unprotected(self)
+    }
+    pub fn try_resolve<'a, M>(&'a self) -> Result<M::Ret, Error<'a, Key>>
+        where M: Method<'a, Key, SvcBase>
+    {
+        let _guard = self.deadlock_protection.lock();
+        M::try_resolve_unprotected(self)
+    }
+}
+
+/// Lists the services by key; services which are currently locked for writing show up as 
+/// `<locked>`.
+impl<Key, SvcBase: ?Sized> Debug for Container<Key, SvcBase>
+    where Key: reflect::Key, SvcBase: Any + Debug
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut map = fmt.debug_map();
+        for (key, svc) in &self.services {
+            match svc.try_read() {
+                Ok(svc) => map.entry(key, &&**svc),
+                Err(_) => map.entry(key, &format_args!("<locked>")),
+            };
+        }
+        map.finish()
+    }
+}
+
+// ++++++++++++++++++++ DebugBase ++++++++++++++++++++
+
+/// Service base for containers whose services are all `Debug`, making the container itself 
+/// `Debug`.
+pub trait DebugBase: downcast::Any + Debug + Send + Sync {}
+
+impl<T> DebugBase for T
+    where T: Any + Debug + Send + Sync
+{}
+
+mod debug_base {
+    #![allow(clippy::transmute_ptr_to_ref)]
+    use super::DebugBase;
+
+    impl_downcast!(DebugBase);
+}
+
+// ++++++++++++++++++++ AsAny ++++++++++++++++++++
+
+/// Service bases which can be viewed as `dyn Any`. Trait object bases implement it by 
+/// upcasting, e.g. `impl AsAny for dyn Base { fn as_any(&self) -> &dyn Any { self } }`.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// ++++++++++++++++++++ AuditReport ++++++++++++++++++++
+
+/// Inconsistencies found by `Container::audit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport<'a, Key: 'a> {
+    /// Keys with settings (descriptions, ...) but no service.
+    pub orphaned: Vec<&'a Key>,
+    /// Deprecated keys whose replacement has no service.
+    pub dangling_deprecations: Vec<&'a Key>,
+    /// Keys whose service is poisoned.
+    pub poisoned: Vec<&'a Key>,
+}
+
+impl<'a, Key> AuditReport<'a, Key> {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned.is_empty() && self.dangling_deprecations.is_empty() && self.poisoned.is_empty()
+    }
+}
+
+// ++++++++++++++++++++ Snapshot ++++++++++++++++++++
+
+/// Copy of all services of a builder along with their settings, see 
+/// `ContainerBuilder::snapshot`.
+pub struct Snapshot<Key, SvcBase: ?Sized> {
+    services: BTreeMap<Key, Box<SvcBase>>,
+    meta: BTreeMap<Key, Meta>,
+    marked: HashMap<TypeId, Vec<(Key, ErasedCast)>>,
+    reserved: BTreeSet<Key>,
+}
+
+impl<Key: Clone, SvcBase: ?Sized> Clone for Snapshot<Key, SvcBase>
+    where Box<SvcBase>: Clone
+{
+    fn clone(&self) -> Self {
+        Snapshot{
+            services: self.services.clone(),
+            meta: self.meta.clone(),
+            marked: self.marked.clone(),
+            reserved: self.reserved.clone(),
+        }
+    }
+}
+
+// ++++++++++++++++++++ ContainerBuilder ++++++++++++++++++++
+
+pub struct ContainerBuilder<Key: Ord, SvcBase: ?Sized> {
+    cont: Container<Key, SvcBase>,
+    reserved: BTreeSet<Key>,
+    dependencies: Option<BTreeMap<Key, BTreeSet<Key>>>,
+    baseline: Option<Snapshot<Key, SvcBase>>,
+}
+
+// Type which `ContainerBuilder::constrain_type` requires under a key.
+struct TypeConstraint<SvcBase: ?Sized> {
+    is_type: fn(&SvcBase) -> bool,
+    type_name: &'static str,
+}
+
+impl<SvcBase: ?Sized> Clone for TypeConstraint<SvcBase> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<SvcBase: ?Sized> Copy for TypeConstraint<SvcBase> {}
+
+// Re-keys `map` through `rekey`; of entries which end up under the same key, the last one wins.
+fn rekeyed<Key: Ord, V, F>(map: BTreeMap<Key, V>, rekey: F) -> BTreeMap<Key, V>
+    where F: Fn(&Key) -> Key
+{
+    map.into_iter().map(|(key, value)| (rekey(&key), value)).collect()
+}
+
+fn is_type<Svc, SvcBase>(svc: &SvcBase) -> bool
+    where Svc: Any, SvcBase: ?Sized + Downcast<Svc>
+{
+    svc.is_type()
+}
+
+impl<Key, SvcBase: ?Sized> ContainerBuilder<Key, SvcBase>
+    where Key: reflect::Key, SvcBase: Any
+{
+    pub fn new() -> Self {
+        ContainerBuilder{ 
+            cont: Container::new(), 
+            reserved: BTreeSet::new(), 
+            dependencies: None,
+            baseline: None,
+        }
+    }
+
+    /// Creates a builder with one service per `(key, type name)` entry of `config`, each 
+    /// constructed through `registry`.
+    pub fn from_config<'a>(
+        config: &'a [(Key, String)], 
+        registry: &TypeRegistry<SvcBase>
+    ) -> Result<Self, BuildError<'a, Key>> {
+        let mut ret = Self::new();
+        for (key, type_name) in config {
+            match registry.create(type_name) {
+                Some(svc) => ret.register_service(key.clone(), svc),
+                None => return Err(BuildError::UnknownType{ key, type_name }),
+            };
+        }
+        Ok(ret)
+    }
+
+    pub fn register_service(&mut self, key: Key, svc: Box<SvcBase>) -> &mut Self {
+        self.cont.register_service(key, svc);
+        self
+    }
+
+    /// Registers `svc` under `key` along with a set of `tags`, to be looked up via 
+    /// `Container::keys_with_tag`.
+    pub fn register_tagged(&mut self, key: Key, svc: Box<SvcBase>, tags: BTreeSet<String>) -> &mut Self {
+        self.cont.register_tagged(key, svc, tags);
+        self
+    }
+
+    /// Registers the service built by `f` under `key`, or fails with `CreationError` if `f` does.
+    pub fn register_try<'a, E, F>(&mut self, key: &'a Key, f: F) -> Result<&mut Self, Error<'a, Key>>
+        where E: StdError + 'static, F: FnOnce() -> Result<Box<SvcBase>, E>
+    {
+        match f() {
+            Ok(svc) => Ok(self.register_service(key.clone(), svc)),
+            Err(error) => Err(Error::CreationError{ key, error: Box::new(error) }),
+        }
+    }
+
+    /// Requires services registered under `key` to be of type `Svc`. See `register_checked` 
+    /// for registering services which might not be.
+    ///
+    /// NOTE: From now on, registering a service of another type under `key` panics. So does 
+    /// `build` if one was registered there before; `build_validated` reports those instead.
+    pub fn constrain_type<Svc>(&mut self, key: Key) -> &mut Self
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        let key = self.cont.normalized(&key).into_owned();
+        let constraint = TypeConstraint{ is_type: is_type::<Svc, SvcBase>, type_name: type_name::<Svc>() };
+        self.cont.constraints.insert(key, constraint);
+        self
+    }
+
+    /// Like `register_service`, but fails with `MismatchedType` (leaving the builder untouched) 
+    /// if `svc` isn't of the type required via `constrain_type`.
+    pub fn register_checked<'a>(&mut self, key: &'a Key, svc: Box<SvcBase>) -> Result<&mut Self, Error<'a, Key>> {
+        if let Some(constraint) = self.cont.constraints.get(&*self.cont.normalized(key)) {
+            if !(constraint.is_type)(&*svc) {
+                return Err(Error::MismatchedType{ 
+                    key, 
+                    expected: constraint.type_name,
+                    found: type_name::<SvcBase>(),
+                });
+            }
+        }
+        Ok(self.register_service(key.clone(), svc))
+    }
+
+    /// Registers `svc` under `key` along with an argument for `Init::init`, which `build_init` 
+    /// passes to it, e.g. the path for a file logger.
+    pub fn register_with_arg(&mut self, key: Key, svc: Box<SvcBase>, arg: Box<dyn Any + Send + Sync>) -> &mut Self {
+        let normalized = self.cont.normalized(&key).into_owned();
+        self.cont.register_service(key, svc);
+        self.cont.meta.entry(normalized).or_default().init_arg = Some(Arc::from(arg));
+        self
+    }
+
+    /// Registers `svc` under `key` unless something is registered there already. Services 
+    /// registered later on replace it as usual.
+    pub fn register_fallback(&mut self, key: Key, svc: Box<SvcBase>) -> &mut Self {
+        if !self.cont.services.contains_key(&*self.cont.normalized(&key)) {
+            self.cont.register_service(key, svc);
+        }
+        self
+    }
+
+    /// Registers `svc` under `key` with a priority for `Container::iter_of`, where services with 
+    /// higher priorities come first. Services registered otherwise have priority `0`.
+    pub fn register_with_priority(&mut self, key: Key, svc: Box<SvcBase>, priority: i32) -> &mut Self {
+        let normalized = self.cont.normalized(&key).into_owned();
+        self.cont.register_service(key, svc);
+        self.cont.meta.entry(normalized).or_default().priority = priority;
+        self
+    }
+
+    /// Registers `svc` under `key`, but lookups only find it while `gate` returns `true`, e.g. 
+    /// for services behind a feature flag. Otherwise they fail with `NotFound`.
+    pub fn register_gated<F>(&mut self, key: Key, svc: Box<SvcBase>, gate: F) -> &mut Self
+        where F: Fn() -> bool + Send + Sync + 'static
+    {
+        let normalized = self.cont.normalized(&key).into_owned();
+        self.cont.register_service(key, svc);
+        self.cont.meta.entry(normalized).or_default().gate = Some(Arc::new(gate));
+        self
+    }
+
+    /// Registers every `(key, service)` pair in `svcs`; like `register_service`, later entries 
+    /// replace earlier ones with the same key.
+    ///
+    /// Also imports plain maps of services, e.g. those handed out by 
+    /// `Container::into_service_map`.
+    pub fn register_services<I>(&mut self, svcs: I) -> &mut Self
+        where I: IntoIterator<Item = (Key, Box<SvcBase>)>
+    {
+        for (key, svc) in svcs {
+            self.cont.register_service(key, svc);
+        }
+        self
+    }
+
+    /// NOTE: The `Box<Svc>: Into<Box<Base>>`-clause is needed due to rusts lack of 
+    /// HKT or a `Coercible`-trait (to name two solutions).
+    pub fn register<Svc>(&mut self, svc: Svc) -> &mut Self
+    where
+        Svc: reflect::Service<Key = Key> + Into<Box<SvcBase>>,
+    {
+        self.cont.register::<Svc>(svc);
+        self
+    }
+
+    pub fn register_default<Svc>(&mut self) -> &mut Self
+    where
+        Svc: Default + reflect::Service<Key = Key> + Into<Box<SvcBase>>,
+    {
+        self.cont.register_default::<Svc>();
+        self
+    }
+
+    /// Registers a clone of `svc` under each of `keys`, so all of them resolve to the same 
+    /// instance (as `Arc<T>`).
+    pub fn register_shared<T>(&mut self, keys: &[Key], svc: Arc<T>) -> &mut Self
+        where Arc<T>: Into<Box<SvcBase>>
+    {
+        for key in keys {
+            self.cont.register_typed::<Arc<T>>(key.clone(), svc.clone().into());
+        }
+        self
+    }
+
+    /// Registers a pinned service under `key`, to be resolved as `Pin<&T>`/`Pin<&mut T>` via 
+    /// `Container::read_pinned`/`write_pinned`.
+    pub fn register_pinned<T>(&mut self, key: Key, svc: Pin<Box<T>>) -> &mut Self
+        where T: ?Sized, Pin<Box<T>>: Into<Box<SvcBase>>
+    {
+        self.cont.register_typed::<Pin<Box<T>>>(key, svc.into());
+        self
+    }
+
+    /// Registers `svc` under `key` and makes `Container::for_each_marked` hand it out as 
+    /// `Marker`, e.g. to start every service which is `dyn Startable`. `as_marker` is usually 
+    /// just `|svc| svc`.
+    pub fn register_as<Svc, Marker>(&mut self, key: Key, svc: Svc, as_marker: fn(&Svc) -> &Marker) -> &mut Self
+        where Svc: Any + Into<Box<SvcBase>>, SvcBase: Downcast<Svc>, Marker: ?Sized + Any
+    {
+        let normalized = self.cont.normalized(&key).into_owned();
+        self.cont.register_typed::<Svc>(key, svc.into());
+        let cast: MarkerCast<SvcBase, Marker> = Box::new(move |svc| Downcast::<Svc>::downcast_ref(svc).map(as_marker));
+        self.cont.marked.entry(TypeId::of::<Marker>()).or_default().push((normalized, Arc::new(cast)));
+        self
+    }
+
+    /// Registers `f` as a service under `key`, to be invoked through `Container::call`.
+    pub fn register_fn<Args, R, F>(&mut self, key: Key, f: F) -> &mut Self
+    where
+        F: Fn(Args) -> R + Send + Sync + 'static,
+        ServiceFn<Args, R>: Into<Box<SvcBase>>,
+    {
+        let f: ServiceFn<Args, R> = Box::new(f);
+        self.cont.register_typed::<ServiceFn<Args, R>>(key, f.into());
+        self
+    }
+
+    /// Moves the services under `keys` into `other`, along with their tags and other settings, 
+    /// e.g. when splitting a container into modules. Fails without moving anything if one of 
+    /// `keys` is missing here or already taken in `other`.
+    pub fn transfer_to<'a>(&mut self, other: &mut Self, keys: &'a [Key]) -> Result<(), TransferError<'a, Key>> {
+        for key in keys {
+            if !self.cont.services.contains_key(&*self.cont.normalized(key)) {
+                return Err(TransferError::NotFound{ key });
+            }
+            if other.cont.services.contains_key(&*other.cont.normalized(key)) {
+                return Err(TransferError::AlreadyExists{ key });
+            }
+            let svc = &self.cont.services[&*self.cont.normalized(key)];
+            other.cont.check_constraint(&other.cont.normalized(key), &**svc.read().unwrap_or_else(PoisonError::into_inner));
+        }
+        for key in keys {
+            let normalized = self.cont.normalized(key).into_owned();
+            if let Some(svc) = self.cont.services.remove(&normalized) {
+                self.cont.emit(ContainerEvent::Removed{ key: &normalized });
+                let dest = other.cont.normalized(key).into_owned();
+                other.cont.emit(ContainerEvent::Registered{ key: &dest });
+                if let Some(meta) = self.cont.meta.remove(&normalized) {
+                    other.cont.meta.insert(dest.clone(), meta);
+                }
+                let marked = self.cont.take_marked(&normalized);
+                other.cont.put_marked(&dest, marked);
+                other.cont.services.insert(dest, svc);
+            }
+        }
+        other.cont.invalidate_type_cache();
+        Ok(())
+    }
+
+    /// Registers a plain configuration value under `key`, to be read back through 
+    /// `Container::value`.
+    pub fn register_value<T>(&mut self, key: Key, value: T) -> &mut Self
+        where T: Any + Clone + Into<Box<SvcBase>>
+    {
+        self.cont.register_typed::<T>(key, value.into());
+        self
+    }
+
+    /// Moves the service registered under `old` to `new`, e.g. when migrating to a new 
+    /// naming scheme.
+    pub fn rename_service<'a>(&mut self, old: &'a Key, new: Key) -> Result<(), RenameError<'a, Key>> {
+        self.cont.rename_service(old, new)
+    }
+
+    /// Adds a hook which gets to see every service looked up through the `*_service_base` 
+    /// methods (and everything built on them) right after it was locked, e.g. for tracing or 
+    /// access control. If any hook returns `false`, the lookup fails with `NotFound`. Hooks see 
+    /// the key the service is registered under, i.e. normalized and past deprecated aliases.
+    ///
+    /// NOTE: `Container::map_services` drops all hooks, since they're tied to the base type.
+    pub fn with_middleware(&mut self, middleware: Middleware<Key, SvcBase>) -> &mut Self {
+        self.cont.middleware.push(middleware);
+        self
+    }
+
+    /// Keeps `old` working as an alias of `new` while no service is registered under `old` 
+    /// itself, running `warn` the first time it is resolved that way.
+    pub fn deprecate(&mut self, old: Key, new: Key, warn: DeprecationWarning<Key>) -> &mut Self {
+        let old = self.cont.normalized(&old).into_owned();
+        let replacement = self.cont.normalized(&new).into_owned();
+        self.cont.deprecated.insert(old, Deprecation{ replacement, warn, warned: Once::new() });
+        self
+    }
+
+    /// Copies every service registered so far along with its settings (tags, descriptions, 
+    /// ...) and markers, as well as the reserved keys, to be put back via `restore`.
+    pub fn snapshot(&self) -> Snapshot<Key, SvcBase>
+        where Box<SvcBase>: Clone
+    {
+        let services = self.cont.services.iter()
+            .map(|(key, svc)| (key.clone(), svc.read().unwrap_or_else(PoisonError::into_inner).clone()))
+            .collect();
+        Snapshot{ 
+            services, 
+            meta: self.cont.meta.clone(), 
+            marked: self.cont.marked.clone(), 
+            reserved: self.reserved.clone(),
+        }
+    }
+
+    /// Puts back everything copied into `snapshot`, dropping the services registered since. 
+    /// Deprecations, middleware and subscribers stay as they are. Subscribers see the dropped 
+    /// services as removed and all others as registered anew.
+    pub fn restore(&mut self, snapshot: Snapshot<Key, SvcBase>) -> &mut Self {
+        let old = ::std::mem::take(&mut self.cont.services);
+        for key in old.keys().filter(|key| !snapshot.services.contains_key(key)) {
+            self.cont.emit(ContainerEvent::Removed{ key });
+        }
+        for key in snapshot.services.keys() {
+            self.cont.emit(ContainerEvent::Registered{ key });
+        }
+        self.cont.services = snapshot.services.into_iter().map(|(key, svc)| (key, RwLock::new(svc))).collect();
+        self.cont.meta = snapshot.meta;
+        self.cont.marked = snapshot.marked;
+        self.reserved = snapshot.reserved;
+        self.cont.invalidate_type_cache();
+        self
+    }
+
+    /// Remembers the services registered so far, along with everything `snapshot` copies, as the 
+    /// defaults to go back to via `reset_to_baseline`.
+    pub fn set_baseline(&mut self) -> &mut Self
+        where Box<SvcBase>: Clone
+    {
+        self.baseline = Some(self.snapshot());
+        self
+    }
+
+    /// Restores the services remembered by `set_baseline`, like `restore` does; services 
+    /// registered after the baseline was set are dropped. Does nothing if no baseline was set.
+    pub fn reset_to_baseline(&mut self) -> &mut Self
+        where Box<SvcBase>: Clone
+    {
+        if let Some(baseline) = self.baseline.clone() {
+            self.restore(baseline);
+        }
+        self
+    }
+
+    /// Makes `subscriber` observe every service registered, renamed or removed from now on, 
+    /// including changes to the built container.
+    pub fn subscribe(&mut self, subscriber: Subscriber<Key>) -> &mut Self {
+        self.cont.subscribers.push(subscriber);
+        self
+    }
+
+    /// Makes `Container::read_unique` remember which key held the service of a given type, 
+    /// instead of scanning all services on every call. Types which a service behind a gate (see 
+    /// `register_gated`) might have are never cached.
+    ///
+    /// NOTE: Replacing a service through a write guard can make a cached type ambiguous without 
+    /// the cache noticing; cached keys which no longer hold the type are detected though.
+    pub fn with_type_cache(&mut self) -> &mut Self {
+        self.cont.type_cache = Some(Mutex::new(HashMap::new()));
+        self
+    }
+
+    /// Attaches a description to `key`, e.g. for generating documentation of the configuration. 
+    /// It may come before the service itself, and stays when the service gets replaced.
+    pub fn document(&mut self, key: Key, doc: String) -> &mut Self {
+        let key = self.cont.normalized(&key).into_owned();
+        self.cont.meta.entry(key).or_default().doc = Some(doc);
+        self
+    }
+
+    /// Declares that a service will be registered under `key` later on, e.g. by a loader which 
+    /// discovers keys before their services. Registering the service fulfills the reservation.
+    pub fn reserve(&mut self, key: Key) -> &mut Self {
+        let key = self.cont.normalized(&key).into_owned();
+        self.reserved.insert(key);
+        self
+    }
+
+    /// Returns the reserved keys which still have no service registered under them.
+    pub fn unfulfilled(&self) -> Vec<&Key> {
+        self.reserved.iter().filter(|key| !self.cont.services.contains_key(key)).collect()
+    }
+
+    /// Makes the container count hits and misses per key, see `Container::stats`.
+    ///
+    /// NOTE: Every lookup has to lock the counters, so this is best left off outside of 
+    /// profiling.
+    pub fn with_metrics(&mut self) -> &mut Self {
+        self.cont.stats = Some(Mutex::new(ResolutionStats::default()));
+        self
+    }
+
+    /// Hands `f` the services registered so far next to a fresh builder for new ones, which get 
+    /// registered here once `f` returns. Everything else set up on that builder (reservations, 
+    /// deprecations, markers, middleware, subscribers and type constraints) carries over as well, 
+    /// and it already checks the type constraints set up here.
+    ///
+    /// Useful for plugins which need to read existing services while registering their own.
+    pub fn stage<F>(&mut self, f: F) -> &mut Self
+        where F: FnOnce(&Container<Key, SvcBase>, &mut ContainerBuilder<Key, SvcBase>)
+    {
+        let _ = self.try_stage(|cont, staging| {
+            f(cont, staging);
+            Ok::<_, errors::DummyError>(())
+        });
+        self
+    }
+
+    /// Like `stage`, but only registers the staged services if `f` succeeds, so that a batch of 
+    /// registrations either applies completely or not at all.
+    pub fn try_stage<E, F>(&mut self, f: F) -> Result<(), E>
+        where F: FnOnce(&Container<Key, SvcBase>, &mut ContainerBuilder<Key, SvcBase>) -> Result<(), E>
+    {
+        let mut staging = ContainerBuilder::new();
+        staging.cont.normalize = self.cont.normalize.clone();
+        staging.cont.constraints = self.cont.constraints.clone();
+        let traced = Arc::new(Mutex::new(BTreeMap::<Key, BTreeSet<Key>>::new()));
+        if self.dependencies.is_some() {
+            let trace = Arc::new(Mutex::new(BTreeSet::new()));
+            self.cont.trace = Some(trace.clone());
+            let traced = traced.clone();
+            staging.subscribe(Box::new(move |event| if let ContainerEvent::Registered{ key } = event {
+                let used = ::std::mem::take(&mut *trace.lock().unwrap_or_else(PoisonError::into_inner));
+                traced.lock().unwrap_or_else(PoisonError::into_inner).entry(key.clone()).or_default().extend(used);
+            }));
+        }
+        let res = f(&self.cont, &mut staging);
+        if self.cont.trace.take().is_some() {
+            // The tracing subscriber mustn't carry over along with the staged ones.
+            drop(staging.cont.subscribers.remove(0));
+        }
+        res?;
+        if let Some(ref mut deps) = self.dependencies {
+            let mut traced = traced.lock().unwrap_or_else(PoisonError::into_inner);
+            for key in staging.cont.services.keys() {
+                deps.insert(key.clone(), traced.remove(key).unwrap_or_default());
+            }
+        }
+        for (key, svc) in staging.cont.services {
+            self.cont.emit(ContainerEvent::Registered{ key: &key });
+            self.cont.meta.remove(&key);
+            self.cont.services.insert(key, svc);
+        }
+        self.cont.meta.extend(staging.cont.meta);
+        self.cont.deprecated.extend(staging.cont.deprecated);
+        for (marker, marked) in staging.cont.marked {
+            self.cont.marked.entry(marker).or_default().extend(marked);
+        }
+        self.cont.middleware.extend(staging.cont.middleware);
+        self.cont.subscribers.extend(staging.cont.subscribers);
+        self.reserved.extend(staging.reserved);
+        self.cont.constraints = staging.cont.constraints;
+        self.cont.invalidate_type_cache();
+        Ok(())
+    }
+
+    /// Makes `stage` and `try_stage` record which of the existing services were looked up while 
+    /// staging. Each lookup counts as a dependency of the next service the stage registers, so 
+    /// stages should look up what a service needs right before registering it.
+    pub fn with_dependency_tracing(&mut self) -> &mut Self {
+        self.dependencies.get_or_insert_with(BTreeMap::new);
+        self
+    }
+
+    /// Returns the dependencies recorded so far, by dependent key; empty unless tracing was 
+    /// enabled via `with_dependency_tracing`.
+    pub fn dependency_graph(&self) -> BTreeMap<Key, BTreeSet<Key>> {
+        self.dependencies.clone().unwrap_or_default()
+    }
+
+    /// Makes the container pass every key through `normalizer` on registration and lookup, e.g. 
+    /// to treat `"audio.mixer"` and `"audio/mixer"` alike. Everything set up so far (services, 
+    /// reservations, deprecations, type constraints, ...) is moved to the normalized keys.
+    ///
+    /// NOTE: Panics if two of the services registered so far end up under the same key.
+    pub fn with_normalizer(&mut self, normalizer: Box<dyn KeyNormalizer<Key>>) -> &mut Self {
+        let normalize: Arc<dyn KeyNormalizer<Key>> = Arc::from(normalizer);
+        let rekey = |key: &Key| normalize.normalize(key).into_owned();
+        let mut originals = BTreeMap::new();
+        for key in self.cont.services.keys() {
+            let normalized = rekey(key);
+            if let Some(other) = originals.insert(normalized.clone(), key) {
+                panic!("{:?} and {:?} both normalize to {:?}", other, key, normalized);
+            }
+        }
+        let cont = &mut self.cont;
+        cont.services = rekeyed(::std::mem::take(&mut cont.services), rekey);
+        cont.meta = rekeyed(::std::mem::take(&mut cont.meta), rekey);
+        cont.deprecated = rekeyed(::std::mem::take(&mut cont.deprecated), rekey);
+        for dep in cont.deprecated.values_mut() {
+            dep.replacement = rekey(&dep.replacement);
+        }
+        for (key, _) in cont.marked.values_mut().flatten() {
+            *key = rekey(key);
+        }
+        cont.constraints = rekeyed(::std::mem::take(&mut cont.constraints), rekey);
+        if let Some(ref mut stats) = cont.stats {
+            let stats = stats.get_mut().unwrap_or_else(PoisonError::into_inner);
+            for counts in [&mut stats.hits, &mut stats.misses] {
+                for (key, count) in ::std::mem::take(counts) {
+                    *counts.entry(rekey(&key)).or_insert(0) += count;
+                }
+            }
+        }
+        if let Some(ref mut cache) = cont.type_cache {
+            for key in cache.get_mut().unwrap_or_else(PoisonError::into_inner).values_mut() {
+                *key = rekey(key);
+            }
+        }
+        self.reserved = self.reserved.iter().map(rekey).collect();
+        if let Some(ref mut deps) = self.dependencies {
+            *deps = rekeyed(::std::mem::take(deps), rekey);
+            for used in deps.values_mut() {
+                *used = used.iter().map(rekey).collect();
+            }
+        }
+        if let Some(ref mut baseline) = self.baseline {
+            baseline.services = rekeyed(::std::mem::take(&mut baseline.services), rekey);
+            baseline.meta = rekeyed(::std::mem::take(&mut baseline.meta), rekey);
+            for (key, _) in baseline.marked.values_mut().flatten() {
+                *key = rekey(key);
+            }
+            baseline.reserved = baseline.reserved.iter().map(rekey).collect();
+        }
+        self.cont.normalize = Some(normalize);
+        self
+    }
+
+    /// Lets `plugin` register its services (and whatever else it needs) in one go.
+    pub fn install<P>(&mut self, plugin: P) -> &mut Self
+        where P: SelfRegister<Key, SvcBase>
+    {
+        plugin.register_into(self);
+        self
+    }
+
+    /// NOTE: Panics if a service isn't of the type required via `constrain_type`.
+    pub fn build(self) -> Container<Key, SvcBase> {
+        let mismatched = self.mismatched();
+        assert!(mismatched.is_empty(), "services under {:?} aren't of the types required via `constrain_type`", mismatched);
+        self.cont
+    }
+
+    // Returns the keys whose service isn't of the type required via `constrain_type`.
+    fn mismatched(&self) -> Vec<Key> {
+        self.cont.constraints.iter()
+            .filter(|&(key, constraint)| match self.cont.services.get(key) {
+                Some(svc) => !(constraint.is_type)(&**svc.read().unwrap_or_else(PoisonError::into_inner)),
+                None => false,
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Like `build`, but fails with the reserved keys which still have no service registered 
+    /// under them, see `reserve`.
+    pub fn build_strict(self) -> Result<Container<Key, SvcBase>, Vec<Key>> {
+        let unfulfilled: Vec<_> = self.unfulfilled().into_iter().cloned().collect();
+        if unfulfilled.is_empty() {
+            Ok(self.build())
+        } else {
+            Err(unfulfilled)
+        }
+    }
+
+    /// Like `build`, but fails with the keys whose service isn't of the type required via 
+    /// `constrain_type` instead of panicking, e.g. because the constraint was only added after 
+    /// the service got registered. The services are checked as they are, without constructing 
+    /// them again.
+    pub fn build_validated(self) -> Result<Container<Key, SvcBase>, Vec<Key>> {
+        let mismatched = self.mismatched();
+        if mismatched.is_empty() {
+            Ok(self.cont)
+        } else {
+            Err(mismatched)
+        }
+    }
+
+    /// Like `build`, but first hands every service registered via `register_with_arg` its 
+    /// argument through `Init::init`.
+    pub fn build_init(mut self) -> Container<Key, SvcBase>
+        where SvcBase: Init
+    {
+        for (key, meta) in &mut self.cont.meta {
+            if let (Some(arg), Some(svc)) = (meta.init_arg.take(), self.cont.services.get_mut(key)) {
+                svc.get_mut().unwrap_or_else(PoisonError::into_inner).init(&*arg);
+            }
+        }
+        self.build()
+    }
+}
+
+impl<SvcBase: ?Sized> ContainerBuilder<String, SvcBase>
+    where SvcBase: Any
+{
+    /// Creates a builder whose container ignores the case of its keys, e.g. `"Logger"` and 
+    /// `"logger"` refer to the same service.
+    ///
+    /// NOTE: Every registration and lookup of a key containing uppercase letters lowercases it 
+    /// into a fresh `String`, and `Container::services` only ever reports the lowercased keys.
+    pub fn new_case_insensitive() -> Self {
+        let mut ret = Self::new();
+        ret.with_normalizer(Box::new(Lowercase));
+        ret
+    }
+}
+
+impl<Key, SvcBase: ?Sized> Default for ContainerBuilder<Key, SvcBase> 
+    where Key: reflect::Key, SvcBase: Any
+{
+    fn default() -> Self { Self::new() }
+}
+
+// ++++++++++++++++++++ SelfRegister ++++++++++++++++++++
+
+/// Implemented by plugins which know how to register themselves into a `ContainerBuilder`.
+pub trait SelfRegister<Key, SvcBase: ?Sized>
+    where Key: reflect::Key, SvcBase: Any
+{
+    fn register_into(self, builder: &mut ContainerBuilder<Key, SvcBase>);
+}
+
+// ++++++++++++++++++++ KeyNormalizer ++++++++++++++++++++
+
+/// Maps keys onto a canonical form, see `ContainerBuilder::with_normalizer`.
+pub trait KeyNormalizer<Key: Clone>: Send + Sync {
+    fn normalize<'k>(&self, key: &'k Key) -> Cow<'k, Key>;
+}
+
+/// Leaves keys as they are.
+pub struct Identity;
+
+impl<Key: Clone> KeyNormalizer<Key> for Identity {
+    fn normalize<'k>(&self, key: &'k Key) -> Cow<'k, Key> {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Lowercases keys, see `ContainerBuilder::new_case_insensitive`.
+pub struct Lowercase;
+
+impl KeyNormalizer<String> for Lowercase {
+    fn normalize<'k>(&self, key: &'k String) -> Cow<'k, String> {
+        if key.chars().any(char::is_uppercase) {
+            Cow::Owned(key.to_lowercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+/// Strips leading and trailing whitespace from keys.
+pub struct Trim;
+
+impl KeyNormalizer<String> for Trim {
+    fn normalize<'k>(&self, key: &'k String) -> Cow<'k, String> {
+        let trimmed = key.trim();
+        if trimmed.len() == key.len() {
+            Cow::Borrowed(key)
+        } else {
+            Cow::Owned(trimmed.to_owned())
+        }
+    }
+}
+
+// ++++++++++++++++++++ TypeRegistry ++++++++++++++++++++
+
+/// Constructors by type name, for building containers from configuration via 
+/// `ContainerBuilder::from_config`.
+pub struct TypeRegistry<SvcBase: ?Sized> {
+    ctors: HashMap<String, Box<dyn Fn() -> Box<SvcBase>>>,
+}
+
+impl<SvcBase: ?Sized> TypeRegistry<SvcBase> {
+    pub fn new() -> Self {
+        TypeRegistry{ ctors: HashMap::new() }
+    }
+
+    pub fn register<F>(&mut self, type_name: String, ctor: F) -> &mut Self
+        where F: Fn() -> Box<SvcBase> + 'static
+    {
+        self.ctors.insert(type_name, Box::new(ctor));
+        self
+    }
+
+    pub fn create(&self, type_name: &str) -> Option<Box<SvcBase>> {
+        self.ctors.get(type_name).map(|ctor| ctor())
+    }
+}
+
+impl<SvcBase: ?Sized> Default for TypeRegistry<SvcBase> {
+    fn default() -> Self { Self::new() }
+}
+
+// ++++++++++++++++++++ Init ++++++++++++++++++++
+
+/// Hook invoked by `ContainerBuilder::build_init` for services registered with an argument.
+pub trait Init {
+    fn init(&mut self, arg: &dyn Any);
+}
+
+// ++++++++++++++++++++ OnShutdown ++++++++++++++++++++
+
+/// Hook invoked by `Container::shutdown` right before a service is dropped.
+pub trait OnShutdown {
+    fn on_shutdown(&mut self);
+}
+
+// ++++++++++++++++++++ Resolver ++++++++++++++++++++
+
+/// Object-safe view of a `Container`, for code which only needs to look up services by key.
+pub trait Resolver<Key, SvcBase: ?Sized>
+    where Key: reflect::Key, SvcBase: Any
+{
+    fn read_service_base<'a>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<RwLockReadGuard<'a, Box<SvcBase>>, Error<'a, Key>>;
+
+    fn write_service_base<'a>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<RwLockWriteGuard<'a, Box<SvcBase>>, Error<'a, Key>>;
+}
+
+impl<Key, SvcBase: ?Sized> Resolver<Key, SvcBase> for Container<Key, SvcBase>
+    where Key: reflect::Key, SvcBase: Any
+{
+    fn read_service_base<'a>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<RwLockReadGuard<'a, Box<SvcBase>>, Error<'a, Key>> {
+        Container::read_service_base(self, key)
+    }
+
+    fn write_service_base<'a>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<RwLockWriteGuard<'a, Box<SvcBase>>, Error<'a, Key>> {
+        Container::write_service_base(self, key)
+    }
+}
+
+impl<'r, Key, SvcBase: ?Sized> dyn Resolver<Key, SvcBase> + 'r
+    where Key: reflect::Key, SvcBase: Any
+{
+    pub fn read_service<'a, Svc>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, Key>>
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        let base = self.read_service_base(key)?;
+        ReadGuard::wrap(base).map_err(|_| Error::MismatchedType{ 
+            key, 
+            expected: type_name::<Svc>(),
+            found: type_name::<Svc>(),
+        })
+    }
+
+    pub fn write_service<'a, Svc>(
+        &'a self, 
+        key: &'a Key
+    ) -> Result<WriteGuard<'a, Svc, SvcBase>, Error<'a, Key>>
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        let base = self.write_service_base(key)?;
+        WriteGuard::wrap(base).map_err(|_| Error::MismatchedType{ 
+            key, 
+            expected: type_name::<Svc>(),
+            found: type_name::<Svc>(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    #![allow(clippy::transmute_ptr_to_ref)]
+    use super::*;
+
+    use reflect::Service;
+
+    use std::marker::PhantomPinned;
+    use std::ptr;
+    use std::sync::OnceLock;
+
+    pub trait Base: downcast::Any + Send + Sync {}
+
+    impl<T> Base for T
+        where T: Any + Send + Sync
+    {}
+
+    impl_downcast!(Base);
+
+    macro_rules! service {
+        ($svc:ty, $key:expr) => {
+            impl reflect::Service for $svc {
+                type Key = String;
+                fn key() -> &'static String {
+                    static KEY: OnceLock<String> = OnceLock::new();
+                    KEY.get_or_init(|| $key.to_owned())
+                }
+            }
+
+            impl From<$svc> for Box<dyn Base> {
+                fn from(svc: $svc) -> Self {
+                    Box::new(svc)
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Counter(pub i32);
+    service!(Counter, "counter");
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Name(pub String);
+    service!(Name, "name");
+
+    pub trait Foo: Send + Sync {
+        fn foo(&self) -> &str;
+    }
+
+    service!(Box<dyn Foo>, "foo");
+
+    impl From<Arc<Counter>> for Box<dyn Base> {
+        fn from(svc: Arc<Counter>) -> Self {
+            Box::new(svc)
+        }
+    }
+
+    impl From<ServiceFn<(i32, i32), i32>> for Box<dyn Base> {
+        fn from(f: ServiceFn<(i32, i32), i32>) -> Self {
+            Box::new(f)
+        }
+    }
+
+    impl AsAny for dyn Base {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl From<u16> for Box<dyn Base> {
+        fn from(value: u16) -> Self {
+            Box::new(value)
+        }
+    }
+
+    impl From<String> for Box<dyn Base> {
+        fn from(value: String) -> Self {
+            Box::new(value)
+        }
+    }
+
+    pub struct SelfRef {
+        value: i32,
+        this: *const i32,
+        _pinned: PhantomPinned,
+    }
+
+    unsafe impl Send for SelfRef {}
+    unsafe impl Sync for SelfRef {}
+
+    impl From<Pin<Box<SelfRef>>> for Box<dyn Base> {
+        fn from(svc: Pin<Box<SelfRef>>) -> Self {
+            Box::new(svc)
+        }
+    }
+
+    pub fn key(key: &str) -> String {
+        key.to_owned()
+    }
+
+    pub fn assert_not_found<T>(res: Result<T, Error<String>>, expected: &str) {
+        match res {
+            Err(Error::NotFound{ key }) => assert_eq!(key, expected),
+            Err(err) => panic!("expected `NotFound`, got {}", err),
+            Ok(_) => panic!("expected `NotFound`"),
+        }
+    }
+
+    // Poisons the service under `key` by panicking while it's locked for writing.
+    pub fn poison(cont: &Container<String, dyn Base>, key: &str) {
+        let key = key.to_owned();
+        let poison = ::std::panic::AssertUnwindSafe(|| {
+            let _svc = cont.write_service_base(&key).unwrap();
+            panic!("poison");
+        });
+        assert!(::std::panic::catch_unwind(poison).is_err());
+    }
+
+    pub fn builder() -> ContainerBuilder<String, dyn Base> {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_service(key("counter"), Box::new(Counter(1)));
+        builder.register_service(key("name"), Box::new(Name(key("ioc"))));
+        builder
+    }
+
+    #[test]
+    fn into_service_map_hands_out_every_service() {
+        let map = builder().build().into_service_map();
+        let mut keys: Vec<_> = map.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["counter", "name"]);
+        assert_eq!(Downcast::<Counter>::downcast_ref(&*map["counter"]).unwrap().0, 1);
+    }
+
+    #[test]
+    fn peek_then_modifies_and_returns() {
+        let cont = builder().build();
+        let ret = cont.peek_then::<Counter, _, _>(|counter| {
+            counter.0 += 1;
+            counter.0 * 10
+        });
+        assert_eq!(ret.unwrap(), 20);
+        assert_eq!(cont.read::<Counter>().unwrap().0, 2);
+    }
+
+    #[test]
+    fn install_lets_plugins_register_themselves() {
+        struct Plugin;
+
+        impl SelfRegister<String, dyn Base> for Plugin {
+            fn register_into(self, builder: &mut ContainerBuilder<String, dyn Base>) {
+                builder.register(Counter(3)).register(Name(key("plugin")));
+            }
+        }
+
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.install(Plugin);
+        let cont = builder.build();
+        assert_eq!(cont.read::<Counter>().unwrap().0, 3);
+        assert_eq!(cont.read::<Name>().unwrap().0, "plugin");
+    }
+
+    #[test]
+    fn case_insensitive_keys() {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new_case_insensitive();
+        builder.register_service(key("Logger"), Box::new(Counter(5)));
+        let cont = builder.build();
+        assert_eq!(cont.read_service::<Counter>(&key("LOGGER")).unwrap().0, 5);
+        assert!(cont.services().contains_key("logger"));
+
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_service(key("Logger"), Box::new(Counter(5)));
+        assert!(builder.build().read_service::<Counter>(&key("logger")).is_err());
+    }
+
+    #[test]
+    fn resolve_set_collects_failures() {
+        let mut builder = builder();
+        builder.register_service(key("other"), Box::new(Counter(2)));
+        let cont = builder.build();
+        let keys: Vec<String> = ["counter", "x", "name", "other", "y"].iter().map(|k| key(k)).collect();
+        let res = cont.resolve_set(&keys);
+        let found: Vec<_> = res.found.iter().map(|&(key, _)| key.as_str()).collect();
+        assert_eq!(found, vec!["counter", "name", "other"]);
+        let missing: Vec<_> = res.missing.iter().map(|err| match *err {
+            Error::NotFound{ key } => key.as_str(),
+            ref err => panic!("expected `NotFound`, got {}", err),
+        }).collect();
+        assert_eq!(missing, vec!["x", "y"]);
+    }
+
+    #[test]
+    fn rename_service_moves_the_service() {
+        let mut builder = builder();
+        builder.rename_service(&key("counter"), key("ticks")).unwrap();
+        let cont = builder.build();
+        assert_eq!(cont.read_service::<Counter>(&key("ticks")).unwrap().0, 1);
+        assert!(cont.get_service(&key("counter")).is_none());
+    }
+
+    #[test]
+    fn rename_service_missing_source() {
+        let mut builder = builder();
+        match builder.rename_service(&key("x"), key("y")) {
+            Err(RenameError::NotFound{ key }) => assert_eq!(key, "x"),
+            res => panic!("expected `NotFound`, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn rename_service_colliding_destination() {
+        let mut builder = builder();
+        match builder.rename_service(&key("counter"), key("name")) {
+            Err(RenameError::AlreadyExists{ key }) => assert_eq!(key, "name"),
+            res => panic!("expected `AlreadyExists`, got {:?}", res),
+        }
+        let cont = builder.build();
+        assert_eq!(cont.read::<Counter>().unwrap().0, 1);
+        assert_eq!(cont.read::<Name>().unwrap().0, "ioc");
+    }
+
+    #[test]
+    fn metrics_count_hits_and_misses() {
+        let mut metered = builder();
+        metered.with_metrics();
+        let cont = metered.build();
+        assert!(cont.read::<Counter>().is_ok());
+        assert!(cont.read::<Counter>().is_ok());
+        assert!(cont.write::<Name>().is_ok());
+        assert!(cont.read_service_base(&key("x")).is_err());
+        assert!(cont.try_read_service_base(&key("x")).is_err());
+        cont.resolve_set(&[key("counter"), key("y")]);
+
+        let stats = cont.stats();
+        assert_eq!(stats.hits[&key("counter")], 3);
+        assert_eq!(stats.hits[&key("name")], 1);
+        assert_eq!(stats.misses[&key("x")], 2);
+        assert_eq!(stats.misses[&key("y")], 1);
+        assert_eq!(stats.hits.len() + stats.misses.len(), 4);
+
+        let cont = builder().build();
+        assert!(cont.read::<Counter>().is_ok());
+        assert_eq!(cont.stats(), ResolutionStats::default());
+    }
+
+    #[test]
+    fn call_registered_closure() {
+        let mut builder = builder();
+        builder.register_fn(key("add"), |(a, b): (i32, i32)| a + b);
+        let cont = builder.build();
+        assert_eq!(cont.call::<(i32, i32), i32>(&key("add"), (2, 3)).unwrap(), 5);
+        match cont.call::<i32, i32>(&key("add"), 2) {
+            Err(Error::MismatchedType{ .. }) => {}
+            res => panic!("expected `MismatchedType`, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn get_mut_or_insert_with_constructs_once() {
+        let mut cont = ContainerBuilder::<String, dyn Base>::new().build();
+        let mut created = 0;
+        for _ in 0..2 {
+            let counter = cont.get_mut_or_insert_with(Counter::key(), || {
+                created += 1;
+                Counter(0)
+            }).unwrap();
+            counter.0 += 1;
+        }
+        assert_eq!(created, 1);
+        assert_eq!(cont.read::<Counter>().unwrap().0, 2);
+        assert!(cont.get_mut_or_insert_with(Counter::key(), || Name(key("x"))).is_err());
+    }
+
+    #[test]
+    fn container_as_dyn_resolver() {
+        fn counter_value(ioc: &dyn Resolver<String, dyn Base>, key: &String) -> i32 {
+            ioc.read_service::<Counter>(key).unwrap().0
+        }
+
+        let cont = builder().build();
+        assert_eq!(counter_value(&cont, &key("counter")), 1);
+        let ioc: &dyn Resolver<String, dyn Base> = &cont;
+        assert!(ioc.read_service::<Counter>(&key("name")).is_err());
+        assert!(ioc.read_service_base(&key("x")).is_err());
+    }
+
+    #[test]
+    fn register_services_in_bulk() {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_services((0..4).map(|i| (format!("plugin{}", i), Box::new(Counter(i)) as Box<dyn Base>)));
+        let cont = builder.build();
+        let keys: Vec<_> = cont.services().keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["plugin0", "plugin1", "plugin2", "plugin3"]);
+        assert_eq!(cont.read_service::<Counter>(&key("plugin2")).unwrap().0, 2);
+    }
+
+    #[test]
+    fn read_first_follows_precedence() {
+        let cont = builder().build();
+        let keys = [key("user.counter"), key("name"), key("counter")];
+        assert_eq!(cont.read_first::<Counter>(&keys).unwrap().0, 1);
+
+        let keys = [key("counter"), key("user.name")];
+        assert_not_found(cont.read_first::<Name>(&keys), "user.name");
+    }
+
+    #[test]
+    fn get_disjoint_mut_borrows_both() {
+        let mut cont = builder().build();
+        {
+            let (counter, name) = cont.get_disjoint_mut::<Counter, Name>().unwrap();
+            counter.0 += name.0.len() as i32;
+            name.0.push('!');
+        }
+        assert_eq!(cont.read::<Counter>().unwrap().0, 4);
+        assert_eq!(cont.read::<Name>().unwrap().0, "ioc!");
+    }
+
+    #[test]
+    #[should_panic(expected = "`get_disjoint_mut` called twice")]
+    fn get_disjoint_mut_rejects_same_key() {
+        let mut cont = builder().build();
+        let _ = cont.get_disjoint_mut::<Counter, Counter>();
+    }
+
+    #[test]
+    fn shutdown_respects_order() {
+        trait Finalize: OnShutdown + Any + Send + Sync {}
+
+        impl<T> Finalize for T
+            where T: OnShutdown + Any + Send + Sync
+        {}
+
+        struct Probe(&'static str, Arc<Mutex<Vec<&'static str>>>);
+
+        impl OnShutdown for Probe {
+            fn on_shutdown(&mut self) {
+                self.1.lock().unwrap().push(self.0);
+            }
+        }
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut builder = ContainerBuilder::<String, dyn Finalize>::new();
+        for name in ["a", "b", "c", "d"] {
+            builder.register_service(key(name), Box::new(Probe(name, log.clone())));
+        }
+        builder.build().shutdown(&[key("c"), key("x"), key("a")]);
+        assert_eq!(*log.lock().unwrap(), vec!["c", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn register_shared_under_several_keys() {
+        let mut builder = builder();
+        let keys = [key("ticks"), key("legacy.ticks")];
+        builder.register_shared(&keys, Arc::new(Counter(7)));
+        let cont = builder.build();
+        let a = cont.read_service::<Arc<Counter>>(&keys[0]).unwrap();
+        let b = cont.read_service::<Arc<Counter>>(&keys[1]).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(a.0, 7);
+    }
+
+    #[test]
+    fn explain_resolution() {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_service(key("counter"), Box::new(Name(key("ioc"))));
+        builder.register(Name(key("ioc")));
+        let cont = builder.build();
+        assert_eq!(cont.explain::<Counter>(), ResolutionExplanation::WrongType);
+        assert_eq!(cont.explain::<Name>(), ResolutionExplanation::Ok);
+        {
+            let _name = cont.write::<Name>().unwrap();
+            assert_eq!(cont.explain::<Name>(), ResolutionExplanation::Locked);
+        }
+        poison(&cont, "name");
+        assert_eq!(cont.explain::<Name>(), ResolutionExplanation::Poisoned);
+
+        let cont = ContainerBuilder::<String, dyn Base>::new().build();
+        assert_eq!(cont.explain::<Counter>(), ResolutionExplanation::Absent);
+    }
+
+    #[test]
+    fn explain_has_no_side_effects() {
+        let warned = Arc::new(Mutex::new(0));
+        let count = warned.clone();
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder
+            .with_metrics()
+            .register_service(key("ticks"), Box::new(Counter(1)))
+            .deprecate(key("counter"), key("ticks"), Box::new(move |_: &String, _: &String| *count.lock().unwrap() += 1));
+        let cont = builder.build();
+        assert_eq!(cont.explain::<Counter>(), ResolutionExplanation::Ok);
+        assert_eq!(cont.explain::<Name>(), ResolutionExplanation::Absent);
+        assert_eq!(cont.stats(), ResolutionStats::default());
+        assert_eq!(*warned.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn register_fallback_only_fills_gaps() {
+        let mut builder = builder();
+        builder.register_fallback(key("counter"), Box::new(Counter(10)));
+        builder.register_fallback(key("ticks"), Box::new(Counter(20)));
+        let cont = builder.build();
+        assert_eq!(cont.read::<Counter>().unwrap().0, 1);
+        assert_eq!(cont.read_service::<Counter>(&key("ticks")).unwrap().0, 20);
+
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_fallback(key("counter"), Box::new(Counter(10)));
+        builder.register(Counter(30));
+        assert_eq!(builder.build().read::<Counter>().unwrap().0, 30);
+    }
+
+    #[test]
+    fn read_trait_object() {
+        struct FooBar;
+
+        impl Foo for FooBar {
+            fn foo(&self) -> &str { "bar" }
+        }
+
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register(Box::new(FooBar) as Box<dyn Foo>);
+        let ioc = builder.build();
+        assert_eq!("bar", ioc.read::<Box<dyn Foo>>().unwrap().foo());
+    }
+
+    #[test]
+    fn partition_by_poisoning() {
+        let mut builder = builder();
+        builder.register_service(key("other"), Box::new(Counter(2)));
+        let cont = builder.build();
+        poison(&cont, "name");
+        let (healthy, poisoned) = cont.partition();
+        assert_eq!(healthy, vec!["counter", "other"]);
+        assert_eq!(poisoned, vec!["name"]);
+    }
+
+    #[test]
+    fn stage_reads_existing_and_registers_new() {
+        let mut builder = builder();
+        builder.stage(|existing, staging| {
+            let name = existing.read::<Name>().unwrap();
+            staging.register_service(key("greeting"), Box::new(Name(format!("hello {}", name.0))));
+            assert!(existing.get_service(&key("greeting")).is_none());
+        });
+        let cont = builder.build();
+        assert_eq!(cont.read_service::<Name>(&key("greeting")).unwrap().0, "hello ioc");
+    }
+
+    #[test]
+    fn from_config_uses_type_registry() {
+        let mut registry = TypeRegistry::<dyn Base>::new();
+        registry
+            .register(key("Counter"), || Box::new(Counter(0)))
+            .register(key("Name"), || Box::new(Name(key("default"))));
+
+        let config = [(key("ticks"), key("Counter")), (key("name"), key("Name"))];
+        let cont = ContainerBuilder::from_config(&config, &registry).unwrap().build();
+        assert_eq!(cont.read_service::<Counter>(&key("ticks")).unwrap().0, 0);
+        assert_eq!(cont.read::<Name>().unwrap().0, "default");
+
+        let config = [(key("logger"), key("StdoutLogger"))];
+        match ContainerBuilder::from_config(&config, &registry) {
+            Err(BuildError::UnknownType{ key, type_name }) => {
+                assert_eq!((key.as_str(), type_name), ("logger", "StdoutLogger"));
+            }
+            Ok(_) => panic!("expected `UnknownType`"),
+        }
+    }
+
+    #[test]
+    fn map_services_converts_base() {
+        let mut builder = ContainerBuilder::<String, i32>::new();
+        builder.register_service(key("a"), Box::new(1)).register_service(key("b"), Box::new(2));
+        let cont = builder.build().map_services(|key, svc| Box::new(format!("{}={}", key, svc)));
+        let svcs: Vec<_> = cont.services().values().map(|svc| svc.read().unwrap().to_string()).collect();
+        assert_eq!(svcs, vec!["a=1", "b=2"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "service registered under the blank key \" \\t\"")]
+    fn blank_keys_are_rejected() {
+        builder().register_service(key(" \t"), Box::new(Counter(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "registered under the blank key \"\", see its `Service::key` impl")]
+    fn blank_service_keys_are_rejected() {
+        struct Blank;
+        service!(Blank, "");
+
+        builder().register(Blank);
+    }
+
+    #[test]
+    fn read_with_type_reports_type_id() {
+        let cont = builder().build();
+        let (counter, type_id) = cont.read_with_type::<Counter>().unwrap();
+        assert_eq!(counter.0, 1);
+        assert_eq!(type_id, TypeId::of::<Counter>());
+    }
+
+    #[test]
+    fn nested_containers() {
+        let mut mixer = ContainerBuilder::<String, dyn Base>::new();
+        mixer.register(Counter(48));
+        let mut audio = ContainerBuilder::<String, dyn Base>::new();
+        audio.register_service(key("mixer"), Box::new(mixer.build()));
+        let mut builder = builder();
+        builder.register_service(key("audio"), Box::new(audio.build()));
+        let cont = builder.build();
+
+        let audio = key("audio");
+        assert!(cont.sub(&audio).unwrap().sub(&key("mixer")).is_ok());
+        let path = [key("audio"), key("mixer"), key("counter")];
+        assert_eq!(cont.with_path(&path, |counter: &Counter| counter.0).unwrap(), 48);
+
+        let path = [key("audio"), key("video"), key("counter")];
+        assert_not_found(cont.with_path(&path, |counter: &Counter| counter.0), "video");
+    }
+
+    #[test]
+    fn all_registered_and_missing_of() {
+        let cont = builder().build();
+        let keys = [key("counter"), key("name")];
+        assert!(cont.all_registered(&keys));
+        assert!(cont.missing_of(&keys).is_empty());
+
+        let keys = [key("counter"), key("logger"), key("name"), key("clock")];
+        assert!(!cont.all_registered(&keys));
+        assert_eq!(cont.missing_of(&keys), vec!["logger", "clock"]);
+    }
+
+    #[test]
+    fn iter_mut_owned_keys_outlive_borrow() {
+        let mut builder = ContainerBuilder::<String, i32>::new();
+        builder.register_service(key("a"), Box::new(1)).register_service(key("b"), Box::new(2));
+        let mut cont = builder.build();
+        let results: Vec<(String, i32)> = cont.iter_mut_owned_keys()
+            .map(|(key, svc)| {
+                **svc *= 10;
+                (key, **svc)
+            })
+            .collect();
+        assert_eq!(results, vec![(key("a"), 10), (key("b"), 20)]);
+        assert_eq!(*cont.read_service_base(&key("b")).unwrap().as_ref(), 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "\"Counter\" and \"counter\" both normalize to \"counter\"")]
+    fn normalizer_rejects_colliding_keys() {
+        let mut builder = builder();
+        builder.register_service(key("Counter"), Box::new(Counter(2)));
+        builder.with_normalizer(Box::new(Lowercase));
+    }
+
+    #[test]
+    fn normalizer_rekeys_stats() {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.with_metrics().register_service(key("Ticks"), Box::new(Counter(1)));
+        builder.stage(|existing, _| {
+            assert!(existing.read_service::<Counter>(&key("Ticks")).is_ok());
+            assert!(existing.read_service::<Counter>(&key("TICKS")).is_err());
+        });
+        builder.with_normalizer(Box::new(Lowercase));
+        let stats = builder.build().stats();
+        assert_eq!(stats.hits.get("ticks"), Some(&1));
+        assert_eq!(stats.misses.get("ticks"), Some(&1));
+    }
+
+    #[test]
+    fn custom_key_normalizer() {
+        struct Dashes;
+
+        impl KeyNormalizer<String> for Dashes {
+            fn normalize<'k>(&self, key: &'k String) -> Cow<'k, String> {
+                Cow::Owned(key.replace('_', "-"))
+            }
+        }
+
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder
+            .register_service(key("event_loop"), Box::new(Counter(1)))
+            .deprecate(key("old_loop"), key("event_loop"), Box::new(|_: &String, _: &String| {}))
+            .register_as::<_, dyn Debug>(key("debug_counter"), Counter(3), |svc| svc)
+            .constrain_type::<Counter>(key("timer_count"));
+        builder.with_normalizer(Box::new(Dashes));
+        builder.register_service(key("job_queue"), Box::new(Counter(2)));
+        assert!(builder.register_checked(&key("timer-count"), Box::new(Name(key("0")))).is_err());
+        let cont = builder.build();
+        assert_eq!(cont.read_service::<Counter>(&key("event-loop")).unwrap().0, 1);
+        assert_eq!(cont.read_service::<Counter>(&key("job_queue")).unwrap().0, 2);
+        assert_eq!(cont.read_service::<Counter>(&key("old-loop")).unwrap().0, 1);
+        let keys: Vec<_> = cont.services().keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["debug-counter", "event-loop", "job-queue"]);
+        let mut marked = Vec::new();
+        cont.for_each_marked::<dyn Debug, _>(|key, _| marked.push(key.clone()));
+        assert_eq!(marked, vec!["debug-counter"]);
+
+        assert_eq!(*Trim.normalize(&key(" name\t")), "name");
+        assert_eq!(*KeyNormalizer::<String>::normalize(&Identity, &key(" Name ")), " Name ");
+    }
+
+    #[test]
+    fn iter_of_yields_matching_types_only() {
+        let mut builder = builder();
+        builder.register_service(key("other"), Box::new(Counter(2)));
+        let cont = builder.build();
+        let counters: Vec<_> = cont.iter_of::<Counter>().map(|(key, svc)| (key.as_str(), svc.0)).collect();
+        assert_eq!(counters, vec![("counter", 1), ("other", 2)]);
+    }
+
+    #[test]
+    fn reserve_and_fulfill() {
+        let mut builder = builder();
+        builder.reserve(key("logger")).reserve(key("clock")).reserve(key("counter"));
+        assert_eq!(builder.unfulfilled(), vec!["clock", "logger"]);
+        builder.register_service(key("logger"), Box::new(Name(key("stdout"))));
+        assert_eq!(builder.unfulfilled(), vec!["clock"]);
+        let cont = builder.build();
+        assert_eq!(cont.read_service::<Name>(&key("logger")).unwrap().0, "stdout");
+        assert!(cont.read_service_base(&key("clock")).is_err());
+    }
+
+    #[test]
+    fn keys_with_tag_filters_by_tag() {
+        let tags = |tags: &[&str]| tags.iter().map(|&tag| key(tag)).collect::<BTreeSet<_>>();
+        let mut builder = builder();
+        builder
+            .register_tagged(key("opus"), Box::new(Name(key("opus"))), tags(&["audio", "lossy"]))
+            .register_tagged(key("flac"), Box::new(Name(key("flac"))), tags(&["audio"]))
+            .register_tagged(key("mp3"), Box::new(Name(key("mp3"))), tags(&["audio", "lossy"]))
+            .register_tagged(key("png"), Box::new(Name(key("png"))), tags(&["image"]));
+        builder.rename_service(&key("mp3"), key("mpeg")).unwrap();
+        builder.register_service(key("opus"), Box::new(Counter(0)));
+        let cont = builder.build();
+        let lossy: Vec<_> = cont.keys_with_tag("lossy").map(String::as_str).collect();
+        assert_eq!(lossy, vec!["mpeg"]);
+        let audio: Vec<_> = cont.keys_with_tag("audio").map(String::as_str).collect();
+        assert_eq!(audio, vec!["flac", "mpeg"]);
+        assert_eq!(cont.keys_with_tag("video").count(), 0);
+    }
+
+    #[test]
+    fn expect_read_succeeds() {
+        let cont = builder().build();
+        assert_eq!(cont.expect_read::<Counter>("counter is always registered").0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "foo is always registered: [\"foo\"] Service could not be found.")]
+    fn expect_read_panics_with_details() {
+        let cont = builder().build();
+        cont.expect_read::<Box<dyn Foo>>("foo is always registered");
+    }
+
+    #[test]
+    fn stage_carries_over_settings() {
+        let mut builder = builder();
+        builder.constrain_type::<Counter>(key("ticks"));
+        builder.stage(|_, staging| {
+            assert!(staging.register_checked(&key("ticks"), Box::new(Name(key("1")))).is_err());
+            staging
+                .reserve(key("clock"))
+                .deprecate(key("count"), key("counter"), Box::new(|_: &String, _: &String| {}))
+                .register_as::<_, dyn Debug>(key("debug"), Counter(2), |svc| svc);
+        });
+        assert_eq!(builder.unfulfilled(), vec!["clock"]);
+        let cont = builder.build();
+        assert_eq!(cont.read_service::<Counter>(&key("count")).unwrap().0, 1);
+        let mut marked = Vec::new();
+        cont.for_each_marked::<dyn Debug, _>(|key, _| marked.push(key.clone()));
+        assert_eq!(marked, vec!["debug"]);
+    }
+
+    #[test]
+    fn try_stage_rolls_back_on_error() {
+        let mut builder = builder();
+        let res = builder.try_stage(|existing, staging| {
+            staging.register_service(key("counter"), Box::new(Counter(10)));
+            staging.register_service(key("clock"), Box::new(Counter(20)));
+            match existing.read_service_base(&key("logger")) {
+                Ok(_) => Ok(()),
+                Err(err) => Err(err.to_string()),
+            }
+        });
+        assert_eq!(res.unwrap_err(), "[\"logger\"] Service could not be found.");
+        let cont = builder.build();
+        assert_eq!(cont.read::<Counter>().unwrap().0, 1);
+        assert!(cont.get_service(&key("clock")).is_none());
+    }
+
+    #[test]
+    fn read_unique_by_type() {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_service(key("main.name"), Box::new(Name(key("ioc"))));
+        builder.register_service(key("a.counter"), Box::new(Counter(1)));
+        builder.register_service(key("b.counter"), Box::new(Counter(2)));
+        let cont = builder.build();
+        assert_eq!(cont.read_unique::<Name>().unwrap().0, "ioc");
+        match cont.read_unique::<Counter>() {
+            Err(Error::Ambiguous{ key }) => assert_eq!(key, "counter"),
+            Err(err) => panic!("expected `Ambiguous`, got {}", err),
+            Ok(_) => panic!("expected `Ambiguous`"),
+        };
+        assert_not_found(cont.read_unique::<Box<dyn Foo>>(), "foo");
+    }
+
+    #[test]
+    fn register_try_surfaces_creation_errors() {
+        use std::io;
+
+        let mut builder = builder();
+        let socket = key("socket");
+        let res = builder.register_try(&socket, || -> Result<Box<dyn Base>, io::Error> {
+            Err(io::Error::new(io::ErrorKind::AddrInUse, "port 80 is taken"))
+        });
+        match res {
+            Err(Error::CreationError{ key, error }) => {
+                assert_eq!(key, "socket");
+                assert_eq!(error.to_string(), "port 80 is taken");
+            }
+            Err(err) => panic!("expected `CreationError`, got {}", err),
+            Ok(_) => panic!("expected `CreationError`"),
+        };
+
+        let clock = key("clock");
+        builder.register_try(&clock, || Ok::<_, io::Error>(Box::new(Counter(3)))).unwrap();
+        assert_eq!(builder.build().read_service::<Counter>(&clock).unwrap().0, 3);
+    }
+
+    #[test]
+    fn type_cache_stays_correct() {
+        let mut cached = builder();
+        cached.with_type_cache();
+        let mut cont = cached.build();
+        assert_eq!(cont.read_unique::<Counter>().unwrap().0, 1);
+        assert_eq!(cont.read_unique::<Counter>().unwrap().0, 1);
+
+        *cont.write_service_base(&key("counter")).unwrap() = Box::new(Name(key("x")));
+        cont.get_mut_or_insert_with(&key("ticks"), || Counter(2)).unwrap();
+        assert_eq!(cont.read_unique::<Counter>().unwrap().0, 2);
+
+        cont.get_mut_or_insert_with(&key("tocks"), || Counter(3)).unwrap();
+        assert!(cont.read_unique::<Counter>().is_err());
+    }
+
+    #[test]
+    fn type_names_of_typed_registrations() {
+        let mut builder = builder();
+        builder.register(Counter(2)).register_shared(&[key("shared")], Arc::new(Counter(3)));
+        let cont = builder.build();
+        let names: Vec<_> = cont.type_names().map(|(key, name)| (key.as_str(), name)).collect();
+        assert_eq!(names, vec![
+            ("counter", type_name::<Counter>()),
+            ("shared", type_name::<Arc<Counter>>()),
+        ]);
+    }
+
+    #[test]
+    fn debug_base_formats_services() {
+        let mut builder = ContainerBuilder::<String, dyn DebugBase>::new();
+        builder.register_service(key("counter"), Box::new(Counter(1)));
+        builder.register_service(key("name"), Box::new(Name(key("ioc"))));
+        let cont = builder.build();
+        assert_eq!(format!("{:?}", cont), r#"{"counter": Counter(1), "name": Name("ioc")}"#);
+        let _name = cont.write::<Name>().unwrap();
+        assert_eq!(cont.read_service::<Counter>(&key("counter")).unwrap().0, 1);
+        assert_eq!(format!("{:?}", cont), r#"{"counter": Counter(1), "name": <locked>}"#);
+    }
+
+    #[test]
+    fn middleware_can_deny_lookups() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let mut builder = builder();
+        builder
+            .with_middleware(Box::new(move |key: &String, _: &dyn Base| {
+                log.lock().unwrap().push(key.clone());
+                true
+            }))
+            .with_middleware(Box::new(|key: &String, _: &dyn Base| key != "name"));
+        let cont = builder.build();
+        assert_eq!(cont.read::<Counter>().unwrap().0, 1);
+        assert_not_found(cont.read::<Name>(), "name");
+        assert_eq!(*seen.lock().unwrap(), vec!["counter", "name"]);
+    }
+
+    #[test]
+    fn range_of_keys() {
+        let mut builder = ContainerBuilder::<String, i32>::new();
+        for (i, name) in ["a", "c", "k", "m", "z"].iter().enumerate() {
+            builder.register_service(key(name), Box::new(i as i32));
+        }
+        let cont = builder.build();
+        let keys: Vec<_> = cont.range(key("a")..=key("m")).map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "c", "k", "m"]);
+        let keys: Vec<_> = cont.range(key("a")..key("m")).map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "c", "k"]);
+        let keys: Vec<_> = cont.range(key("d")..).map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["k", "m", "z"]);
+        assert_eq!(**cont.range(key("k")..).next().unwrap().1, 2);
+    }
+
+    #[test]
+    fn build_init_passes_args() {
+        trait Configurable: Init + downcast::Any + Send + Sync {}
+
+        impl<T> Configurable for T
+            where T: Init + Any + Send + Sync
+        {}
+
+        impl_downcast!(Configurable);
+
+        #[derive(Default)]
+        struct FileLogger(Option<String>);
+
+        impl Init for FileLogger {
+            fn init(&mut self, arg: &dyn Any) {
+                self.0 = arg.downcast_ref::<String>().cloned();
+            }
+        }
+
+        let mut builder = ContainerBuilder::<String, dyn Configurable>::new();
+        builder.register_with_arg(key("logger"), Box::new(FileLogger::default()), Box::new(key("/var/log/app")));
+        builder.register_service(key("stdout"), Box::new(FileLogger::default()));
+        builder
+            .register_with_arg(key("stale"), Box::new(FileLogger::default()), Box::new(key("/tmp/old")))
+            .register_service(key("stale"), Box::new(FileLogger::default()));
+        let cont = builder.build_init();
+        assert_eq!(cont.read_service::<FileLogger>(&key("logger")).unwrap().0.as_ref().unwrap(), "/var/log/app");
+        assert_eq!(cont.read_service::<FileLogger>(&key("stdout")).unwrap().0, None);
+        assert_eq!(cont.read_service::<FileLogger>(&key("stale")).unwrap().0, None);
+    }
+
+    #[test]
+    fn builder_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<ContainerBuilder<String, dyn Base>>();
+        assert_send::<Container<String, dyn Base>>();
+    }
+
+    #[test]
+    fn fingerprint_follows_shape() {
+        let a = builder().build();
+        let b = builder().build();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        let mut builder = builder();
+        builder.register_service(key("counter"), Box::new(Name(key("1"))));
+        assert_ne!(builder.build().fingerprint(), a.fingerprint());
+    }
+
+    #[test]
+    fn pinned_services_stay_put() {
+        let mut svc = Box::pin(SelfRef{ value: 1, this: ptr::null(), _pinned: PhantomPinned });
+        unsafe {
+            let svc = svc.as_mut().get_unchecked_mut();
+            svc.this = &svc.value;
+        }
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_pinned(key("selfref"), svc);
+        let cont = builder.build();
+        let selfref = key("selfref");
+        cont.write_pinned(&selfref, |svc: Pin<&mut SelfRef>| unsafe {
+            svc.get_unchecked_mut().value = 2;
+        }).unwrap();
+        cont.read_pinned(&selfref, |svc: Pin<&SelfRef>| {
+            assert_eq!(svc.this, &svc.value as *const i32);
+            assert_eq!(unsafe { *svc.this }, 2);
+        }).unwrap();
+    }
+
+    #[test]
+    fn documented_keys() {
+        let mut builder = builder();
+        let core = vec![key("core")].into_iter().collect();
+        builder
+            .document(key("counter"), key("Counts the requests served so far."))
+            .document(key("clock"), key("Ticks once a second."))
+            .register_service(key("clock"), Box::new(Counter(0)))
+            .document(key("uptime"), key("Seconds since start."))
+            .register_tagged(key("uptime"), Box::new(Counter(0)), core)
+            .register_service(key("counter"), Box::new(Counter(2)));
+        let cont = builder.build();
+        assert_eq!(cont.doc(&key("counter")), Some("Counts the requests served so far."));
+        assert_eq!(cont.doc(&key("clock")), Some("Ticks once a second."));
+        assert_eq!(cont.doc(&key("uptime")), Some("Seconds since start."));
+        assert_eq!(cont.doc(&key("name")), None);
+    }
+
+    #[test]
+    fn mismatched_type_releases_the_lock() {
+        let cont = builder().build();
+        let counter = key("counter");
+        assert!(cont.write_service::<Name>(&counter).is_err());
+        assert!(cont.read_service::<Name>(&counter).is_err());
+        assert_eq!(cont.write_service::<Counter>(&counter).unwrap().0, 1);
+        assert_eq!(cont.read_service::<Counter>(&counter).unwrap().0, 1);
+    }
+
+    #[test]
+    fn read_cow_clones_on_write() {
+        let cont = builder().build();
+        let owned = cont.read_cow::<Counter, _, _>(|mut counter| {
+            assert!(matches!(counter, Cow::Borrowed(_)));
+            counter.to_mut().0 += 1;
+            counter.into_owned()
+        });
+        assert_eq!(owned.unwrap(), Counter(2));
+        assert_eq!(cont.read::<Counter>().unwrap().0, 1);
+    }
+
+    #[test]
+    fn register_services_from_map() {
+        let mut map = HashMap::new();
+        map.insert(key("a"), Box::new(Counter(1)) as Box<dyn Base>);
+        map.insert(key("b"), Box::new(Counter(2)));
+        map.insert(key("c"), Box::new(Name(key("c"))));
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.register_services(map);
+        let cont = builder.build();
+        assert_eq!(cont.read_service::<Counter>(&key("a")).unwrap().0, 1);
+        assert_eq!(cont.read_service::<Counter>(&key("b")).unwrap().0, 2);
+        assert_eq!(cont.read_service::<Name>(&key("c")).unwrap().0, "c");
+    }
+
+    #[test]
+    fn read_any_downcasts_manually() {
+        let cont = builder().build();
+        let counter = key("counter");
+        let value = cont.read_any(&counter, |svc| svc.downcast_ref::<Counter>().map(|counter| counter.0));
+        assert_eq!(value.unwrap(), Some(1));
+        assert!(cont.read_any(&counter, |svc| svc.is::<Name>()).is_ok_and(|is_name| !is_name));
+    }
+
+    #[test]
+    fn deprecated_keys_warn_once() {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let log = warnings.clone();
+        let mut builder = builder();
+        builder.deprecate(key("ticks"), key("counter"), Box::new(move |old: &String, new: &String| {
+            log.lock().unwrap().push(format!("`{}` is deprecated, use `{}`", old, new));
+        }));
+        let cont = builder.build();
+        let ticks = key("ticks");
+        assert_eq!(cont.read_service::<Counter>(&ticks).unwrap().0, 1);
+        assert_eq!(cont.read_service::<Counter>(&ticks).unwrap().0, 1);
+        assert_eq!(*warnings.lock().unwrap(), vec!["`ticks` is deprecated, use `counter`"]);
+    }
+
+    #[test]
+    fn register_checked_enforces_constraints() {
+        let mut builder = builder();
+        let counter = key("counter");
+        builder.constrain_type::<Counter>(key("counter"));
+        assert!(builder.register_checked(&counter, Box::new(Counter(2))).is_ok());
+        match builder.register_checked(&counter, Box::new(Name(key("x")))) {
+            Err(Error::MismatchedType{ expected, .. }) => assert_eq!(expected, type_name::<Counter>()),
+            Err(err) => panic!("expected `MismatchedType`, got {}", err),
+            Ok(_) => panic!("expected `MismatchedType`"),
+        };
+        assert!(builder.register_checked(&key("name"), Box::new(Counter(3))).is_ok());
+        assert_eq!(builder.build().read::<Counter>().unwrap().0, 2);
+    }
+
+    #[test]
+    fn contains_follows_deprecations() {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new_case_insensitive();
+        builder.register_service(key("counter"), Box::new(Counter(1)));
+        builder.deprecate(key("ticks"), key("counter"), Box::new(|_: &String, _: &String| {}));
+        builder.deprecate(key("tocks"), key("clock"), Box::new(|_: &String, _: &String| {}));
+        let cont = builder.build();
+        assert!(cont.contains(&key("Counter")));
+        assert!(cont.contains(&key("ticks")));
+        assert!(!cont.contains(&key("tocks")));
+        assert!(!cont.contains(&key("name")));
+    }
+
+    #[test]
+    fn gated_services_follow_their_gate() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let enabled = Arc::new(AtomicBool::new(false));
+        let flag = enabled.clone();
+        let mut builder = builder();
+        builder.register_gated(key("beta"), Box::new(Counter(2)), move || flag.load(Ordering::SeqCst));
+        let cont = builder.build();
+        let beta = key("beta");
+        assert_not_found(cont.read_service::<Counter>(&beta), "beta");
+        enabled.store(true, Ordering::SeqCst);
+        assert_eq!(cont.read_service::<Counter>(&beta).unwrap().0, 2);
+    }
+
+    #[test]
+    fn take_service_removes_it() {
+        let mut cont = builder().build();
+        let (counter, name) = (key("counter"), key("name"));
+        assert!(cont.take_service::<Name>(&counter).is_err());
+        assert_eq!(*cont.take_service::<Counter>(&counter).unwrap(), Counter(1));
+        assert_not_found(cont.take_service::<Counter>(&counter), "counter");
+        assert_eq!(cont.read_service::<Name>(&name).unwrap().0, "ioc");
+    }
+
+    #[test]
+    fn matching_type_picks_first_match() {
+        let cont = builder().build();
+        let candidates = [TypeId::of::<Name>(), TypeId::of::<Counter>(), TypeId::of::<i32>()];
+        let counter = key("counter");
+        assert_eq!(cont.matching_type(&counter, &candidates).unwrap(), Some(TypeId::of::<Counter>()));
+        assert_eq!(cont.matching_type(&counter, &candidates[2..]).unwrap(), None);
+    }
+
+    #[test]
+    fn build_strict_rejects_unfulfilled_reservations() {
+        let mut complete = builder();
+        complete.reserve(key("logger")).reserve(key("counter"));
+        complete.register_service(key("logger"), Box::new(Name(key("stdout"))));
+        assert!(complete.build_strict().is_ok());
+
+        let mut incomplete = builder();
+        incomplete.reserve(key("logger")).reserve(key("clock"));
+        match incomplete.build_strict() {
+            Err(missing) => assert_eq!(missing, vec!["clock", "logger"]),
+            Ok(_) => panic!("expected unfulfilled reservations"),
+        };
+    }
+
+    #[test]
+    fn access_counts_include_unused_services() {
+        let mut metered = builder();
+        metered.with_metrics().register_service(key("clock"), Box::new(Counter(0)));
+        let cont = metered.build();
+        assert!(cont.read::<Counter>().is_ok());
+        assert!(cont.read::<Counter>().is_ok());
+        assert!(cont.read::<Name>().is_ok());
+        let counts: Vec<_> = cont.access_counts().into_iter().map(|(key, n)| (key.as_str(), n)).collect();
+        assert_eq!(counts, vec![("clock", 0), ("counter", 2), ("name", 1)]);
+    }
+
+    #[test]
+    fn shared_handles_outlive_the_lock() {
+        use std::thread;
+
+        let mut builder = builder();
+        builder.register_shared(&[key("ticks")], Arc::new(Counter(7)));
+        let cont = Arc::new(builder.build());
+        let task = {
+            let cont = cont.clone();
+            thread::spawn(move || cont.shared::<Counter>(&key("ticks")).map(|ticks| ticks.0).ok())
+        };
+        assert_eq!(task.join().unwrap(), Some(7));
+        let ticks = cont.shared::<Counter>(&key("ticks")).unwrap();
+        assert!(cont.write_service::<Arc<Counter>>(&key("ticks")).is_ok());
+        assert_eq!(ticks.0, 7);
+    }
+
+    #[test]
+    fn same_shape_ignores_state() {
+        let a = builder().build();
+        let b = builder().build();
+        b.write::<Counter>().unwrap().0 = 5;
+        assert!(a.same_shape(&b));
+
+        let mut tagged = builder();
+        tagged.register_tagged(key("name"), Box::new(Name(key("ioc"))), vec![key("core")].into_iter().collect());
+        assert!(!a.same_shape(&tagged.build()));
+    }
+
+    #[test]
+    fn get_or_create_memoizes() {
+        let mut cont = ContainerBuilder::<String, dyn Base>::new().build();
+        cont.get_or_create(|| Counter(1)).unwrap().0 += 1;
+        assert_eq!(cont.get_or_create(|| Counter(10)).unwrap().0, 2);
+        assert_eq!(cont.type_names().count(), 1);
+    }
+
+    #[test]
+    fn register_and_read_values() {
+        let mut builder = builder();
+        builder.register_value(key("port"), 8080u16).register_value(key("host"), key("localhost"));
+        let cont = builder.build();
+        let (port, host) = (key("port"), key("host"));
+        assert_eq!(cont.value::<u16>(&port).unwrap(), 8080);
+        assert_eq!(cont.value::<String>(&host).unwrap(), "localhost");
+        assert!(cont.value::<String>(&port).is_err());
+    }
+
+    #[test]
+    fn dump_lists_keys_and_types() {
+        let mut builder = builder();
+        builder.register_value(key("port"), 8080u16).register(Counter(2));
+        let dump = builder.build().dump();
+        assert_eq!(dump, format!("counter = {}\nname = <untyped>\nport = u16\n", type_name::<Counter>()));
+    }
+
+    #[test]
+    fn enum_keys() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        enum Slot {
+            Clock,
+            Logger,
+        }
+
+        struct Clock(u64);
+
+        impl reflect::Service for Clock {
+            type Key = Slot;
+            fn key() -> &'static Slot {
+                &Slot::Clock
+            }
+        }
+
+        let mut builder = ContainerBuilder::<Slot, dyn Base>::new();
+        builder.register_service(Slot::Clock, Box::new(Clock(42)));
+        builder.register_service(Slot::Logger, Box::new(Name(key("stdout"))));
+        let cont = builder.build();
+        assert_eq!(cont.read::<Clock>().unwrap().0, 42);
+        assert_eq!(cont.read_service::<Name>(&Slot::Logger).unwrap().0, "stdout");
+    }
+
+    #[test]
+    fn disabled_services_are_not_found() {
+        let mut cont = builder().build();
+        let counter = key("counter");
+        cont.set_enabled(&counter, false);
+        assert_not_found(cont.read::<Counter>(), "counter");
+        assert_eq!(cont.read::<Name>().unwrap().0, "ioc");
+        cont.set_enabled(&counter, true);
+        assert_eq!(cont.read::<Counter>().unwrap().0, 1);
+    }
+
+    #[test]
+    fn dependency_tracing_records_lookups() {
+        let mut builder = builder();
+        builder.with_dependency_tracing().register_service(key("clock"), Box::new(Counter(0)));
+        builder.stage(|cont, staging| {
+            let prefix = cont.read_service::<Name>(&key("name")).unwrap().0.clone();
+            let ticks = cont.read_service::<Counter>(&key("clock")).unwrap().0;
+            staging.register_service(key("logger"), Box::new(Name(format!("{}@{}", prefix, ticks))));
+            let count = cont.read::<Counter>().unwrap().0;
+            staging.register_service(key("metrics"), Box::new(Counter(count)));
+        });
+        builder.stage(|_, staging| {
+            staging.register_service(key("standalone"), Box::new(Counter(1)));
+        });
+        let graph = builder.dependency_graph();
+        assert_eq!(graph[&key("logger")].iter().collect::<Vec<_>>(), vec!["clock", "name"]);
+        assert_eq!(graph[&key("metrics")].iter().collect::<Vec<_>>(), vec!["counter"]);
+        assert!(graph[&key("standalone")].is_empty());
+        assert_eq!(builder.build().read_service::<Name>(&key("logger")).unwrap().0, "ioc@0");
+    }
+
+    #[test]
+    fn transfer_services_between_builders() {
+        let mut monolith = builder();
+        let mut module = ContainerBuilder::<String, dyn Base>::new();
+        module.register_service(key("name"), Box::new(Name(key("module"))));
+        let keys = [key("counter"), key("name")];
+        match monolith.transfer_to(&mut module, &keys) {
+            Err(TransferError::AlreadyExists{ key }) => assert_eq!(key, "name"),
+            Err(err) => panic!("expected `AlreadyExists`, got {}", err),
+            Ok(_) => panic!("expected `AlreadyExists`"),
+        };
+        assert_eq!(monolith.cont.services.len(), 2);
+
+        let mut module = ContainerBuilder::<String, dyn Base>::new();
+        monolith.transfer_to(&mut module, &keys).unwrap();
+        assert!(monolith.build().services().is_empty());
+        assert_eq!(module.build().read::<Counter>().unwrap().0, 1);
+    }
+
+    #[test]
+    fn raw_service_points_at_the_service() {
+        let cont = builder().build();
+        let (ptr, type_id) = cont.raw_service(&key("counter")).unwrap();
+        assert_eq!(type_id, TypeId::of::<Counter>());
+        let counter = unsafe { &*(ptr as *const Counter) };
+        assert_eq!(counter.0, 1);
+        assert_eq!(ptr, &*cont.read::<Counter>().unwrap() as *const Counter as *const ());
+    }
+
+    #[test]
+    fn for_each_marked_groups_services() {
+        trait Startable {
+            fn start(&self) -> String;
+        }
+
+        impl Startable for Counter {
+            fn start(&self) -> String {
+                format!("counting from {}", self.0)
+            }
+        }
+
+        impl Startable for Name {
+            fn start(&self) -> String {
+                format!("naming {}", self.0)
+            }
+        }
+
+        let mut builder = builder();
+        builder
+            .register_as::<_, dyn Startable>(key("ticks"), Counter(5), |svc| svc)
+            .register_as::<_, dyn Startable>(key("greeter"), Name(key("world")), |svc| svc);
+        let cont = builder.build();
+        let mut started = Vec::new();
+        cont.for_each_marked::<dyn Startable, _>(|key, svc| started.push(format!("{}: {}", key, svc.start())));
+        assert_eq!(started, vec!["ticks: counting from 5", "greeter: naming world"]);
+    }
+
+    #[test]
+    fn read_or_default_falls_back() {
+        #[derive(Debug, Default, Clone, PartialEq)]
+        struct Retries(u32);
+        service!(Retries, "retries");
+
+        let cont = builder().build();
+        assert_eq!(cont.read_or_default::<Retries>(), Retries(0));
+        let mut builder = builder();
+        builder.register(Retries(3));
+        assert_eq!(builder.build().read_or_default::<Retries>(), Retries(3));
+    }
+
+    #[test]
+    fn subscribers_see_changes() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = events.clone();
+        let mut builder = ContainerBuilder::<String, dyn Base>::new();
+        builder.subscribe(Box::new(move |event: ContainerEvent<String>| {
+            log.lock().unwrap().push(match event {
+                ContainerEvent::Registered{ key } => format!("+{}", key),
+                ContainerEvent::Renamed{ old, new } => format!("{}->{}", old, new),
+                ContainerEvent::Removed{ key } => format!("-{}", key),
+            });
+        }));
+        builder.register(Counter(1)).register_service(key("name"), Box::new(Name(key("ioc"))));
+        builder.rename_service(&key("name"), key("title")).unwrap();
+        let mut cont = builder.build();
+        cont.take_service::<Counter>(&key("counter")).unwrap();
+        assert_eq!(*events.lock().unwrap(), vec!["+counter", "+name", "name->title", "-counter"]);
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = events.clone();
+        let mut builder = ContainerBuilder::<String, i32>::new();
+        builder.register_service(key("a"), Box::new(1)).register_service(key("b"), Box::new(2));
+        let snapshot = builder.snapshot();
+        builder.register_service(key("a"), Box::new(10)).register_service(key("c"), Box::new(3));
+        builder.rename_service(&key("b"), key("d")).unwrap();
+        builder.subscribe(Box::new(move |event: ContainerEvent<String>| {
+            log.lock().unwrap().push(match event {
+                ContainerEvent::Registered{ key } => format!("+{}", key),
+                ContainerEvent::Renamed{ old, new } => format!("{}->{}", old, new),
+                ContainerEvent::Removed{ key } => format!("-{}", key),
+            });
+        }));
+        builder.restore(snapshot);
+        let cont = builder.build();
+        let services: Vec<_> = cont.range(..).map(|(key, svc)| (key.as_str(), **svc)).collect();
+        assert_eq!(services, vec![("a", 1), ("b", 2)]);
+        assert_eq!(*events.lock().unwrap(), vec!["-c", "-d", "+a", "+b"]);
+    }
+
+    #[test]
+    fn restore_brings_back_settings() {
+        let mut builder = ContainerBuilder::<String, i32>::new();
+        let core = vec![key("core")].into_iter().collect();
+        builder
+            .register_tagged(key("b"), Box::new(2), core)
+            .document(key("b"), key("Second."))
+            .reserve(key("e"));
+        let snapshot = builder.snapshot();
+        builder.rename_service(&key("b"), key("d")).unwrap();
+        builder.register_value(key("b"), 20).register_service(key("e"), Box::new(5));
+        builder.restore(snapshot);
+        assert_eq!(builder.unfulfilled(), vec!["e"]);
+        let cont = builder.build();
+        assert_eq!(cont.keys_with_tag("core").collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(cont.doc(&key("b")), Some("Second."));
+        assert_eq!(cont.type_names().count(), 0);
+    }
+
+    #[test]
+    fn iter_of_follows_priorities() {
+        let mut builder = builder();
+        builder
+            .register_with_priority(key("audit"), Box::new(Counter(2)), 10)
+            .register_with_priority(key("metrics"), Box::new(Counter(3)), -5)
+            .register_with_priority(key("access"), Box::new(Counter(4)), 10);
+        let cont = builder.build();
+        let keys: Vec<_> = cont.iter_of::<Counter>().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["access", "audit", "counter", "metrics"]);
+    }
+
+    #[test]
+    fn shared_cell_handles_see_mutations() {
+        trait LocalBase: downcast::Any {}
+
+        impl<T: Any> LocalBase for T {}
+
+        impl_downcast!(LocalBase);
+
+        let mut builder = ContainerBuilder::<String, dyn LocalBase>::new();
+        builder.register_service(key("counter"), Box::new(Rc::new(RefCell::new(Counter(1)))));
+        let cont = builder.build();
+        let counter = cont.shared_cell::<Counter>().unwrap();
+        counter.borrow_mut().0 += 1;
+        assert_eq!(cont.shared_cell::<Counter>().unwrap().borrow().0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "service registered under \"counter\" isn't a")]
+    fn constrained_keys_reject_other_types() {
+        let mut builder = builder();
+        builder.constrain_type::<Counter>(key("counter"));
+        builder.register_value(key("counter"), 7u16);
+    }
+
+    #[test]
+    #[should_panic(expected = "services under [\"name\"] aren't of the types required")]
+    fn build_rejects_mismatched_types() {
+        let mut builder = builder();
+        builder.constrain_type::<Counter>(key("name"));
+        builder.build();
+    }
+
+    #[test]
+    fn build_validated_checks_constraints() {
+        let mut registry = TypeRegistry::<dyn Base>::new();
+        registry.register(key("counter"), || Box::new(Counter(0)));
+        registry.register(key("name"), || Box::new(Name(key("n"))));
+        let config = [(key("ticks"), key("name")), (key("clock"), key("counter"))];
+
+        let mut valid = ContainerBuilder::from_config(&config[1..], &registry).unwrap();
+        valid.constrain_type::<Counter>(key("clock"));
+        assert!(valid.build_validated().is_ok());
+
+        let mut invalid = ContainerBuilder::from_config(&config, &registry).unwrap();
+        invalid.constrain_type::<Counter>(key("ticks")).constrain_type::<Counter>(key("clock"));
+        match invalid.build_validated() {
+            Err(mismatched) => assert_eq!(mismatched, vec!["ticks"]),
+            Ok(_) => panic!("expected a mismatched type"),
+        };
+    }
+
+    #[test]
+    fn reset_to_baseline_restores_defaults() {
+        let mut builder = ContainerBuilder::<String, i32>::new();
+        let core = vec![key("core")].into_iter().collect();
+        builder.register_service(key("a"), Box::new(1)).register_tagged(key("b"), Box::new(2), core);
+        builder.set_baseline();
+        for _ in 0..2 {
+            builder.register_service(key("a"), Box::new(10)).register_value(key("c"), 3);
+            builder.rename_service(&key("b"), key("d")).unwrap();
+            builder.reset_to_baseline();
+            let services: Vec<_> = builder.snapshot().services.into_iter().map(|(key, svc)| (key, *svc)).collect();
+            assert_eq!(services, vec![(key("a"), 1), (key("b"), 2)]);
+        }
+        let cont = builder.build();
+        assert_eq!(cont.keys_with_tag("core").collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(cont.type_names().count(), 0);
+    }
+
+    #[test]
+    fn audit_flags_inconsistencies() {
+        assert!(builder().build().audit().is_clean());
+
+        let mut builder = builder();
+        builder
+            .document(key("logger"), key("Where log messages go."))
+            .deprecate(key("ticks"), key("counter"), Box::new(|_: &String, _: &String| {}))
+            .deprecate(key("tocks"), key("clock"), Box::new(|_: &String, _: &String| {}));
+        let cont = builder.build();
+        poison(&cont, "name");
+        let report = cont.audit();
+        assert_eq!(report.orphaned, vec!["logger"]);
+        assert_eq!(report.dangling_deprecations, vec!["tocks"]);
+        assert_eq!(report.poisoned, vec!["name"]);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn disabled_services_are_hidden_from_scans() {
+        let mut builder = builder();
+        builder.with_type_cache().register_service(key("other"), Box::new(Counter(2)));
+        let mut cont = builder.build();
+        assert_eq!(cont.read_unique::<Name>().unwrap().0, "ioc");
+        cont.set_enabled(&key("name"), false);
+        assert!(cont.read_unique::<Name>().is_err());
+        cont.set_enabled(&key("other"), false);
+        assert_eq!(cont.read_unique::<Counter>().unwrap().0, 1);
+        let counters: Vec<_> = cont.iter_of::<Counter>().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(counters, vec!["counter"]);
+        assert_eq!(cont.explain::<Name>(), ResolutionExplanation::Disabled);
+        assert_eq!(cont.explain::<Counter>(), ResolutionExplanation::Ok);
+    }
+
+    #[test]
+    fn disabled_services_are_hidden_behind_aliases() {
+        let mut builder = builder();
+        builder.deprecate(key("ticks"), key("counter"), Box::new(|_: &String, _: &String| {}));
+        let mut cont = builder.build();
+        let ticks = key("ticks");
+        assert!(cont.contains(&ticks));
+        cont.set_enabled(&key("counter"), false);
+        assert!(!cont.contains(&ticks));
+        assert!(!cont.contains(&key("counter")));
+        assert!(cont.read_service::<Counter>(&ticks).is_err());
+    }
+
+    #[test]
+    fn disabled_services_are_not_marked() {
+        let mut builder = builder();
+        builder
+            .register_as::<_, dyn Debug>(key("a"), Counter(2), |svc| svc)
+            .register_as::<_, dyn Debug>(key("b"), Counter(3), |svc| svc);
+        let mut cont = builder.build();
+        cont.set_enabled(&key("a"), false);
+        let mut marked = Vec::new();
+        cont.for_each_marked::<dyn Debug, _>(|key, _| marked.push(key.clone()));
+        assert_eq!(marked, vec!["b"]);
+    }
+
+    #[test]
+    fn closed_gates_hide_services_from_scans() {
+        let mut builder = builder();
+        builder.register_gated(key("beta"), Box::new(Counter(2)), || false);
+        let cont = builder.build();
+        assert!(!cont.contains(&key("beta")));
+        assert_eq!(cont.iter_of::<Counter>().count(), 1);
+        assert_eq!(cont.read_unique::<Counter>().unwrap().0, 1);
+    }
+
+    #[test]
+    fn marked_services_follow_moves() {
+        fn marked(cont: &Container<String, dyn Base>) -> Vec<String> {
+            let mut ret = Vec::new();
+            cont.for_each_marked::<dyn Debug, _>(|key, _| ret.push(key.clone()));
+            ret
+        }
+
+        let mut builder = builder();
+        builder
+            .register_as::<_, dyn Debug>(key("a"), Counter(2), |svc| svc)
+            .register_as::<_, dyn Debug>(key("b"), Counter(3), |svc| svc)
+            .register_as::<_, dyn Debug>(key("c"), Counter(4), |svc| svc);
+        builder.rename_service(&key("a"), key("renamed")).unwrap();
+        let mut other = ContainerBuilder::new();
+        builder.transfer_to(&mut other, &[key("b")]).unwrap();
+        let mut cont = builder.build();
+        cont.take_service::<Counter>(&key("c")).unwrap();
+        assert_eq!(marked(&cont), vec!["renamed"]);
+        assert_eq!(marked(&other.build()), vec!["b"]);
+    }
+
+    #[test]
+    fn middleware_sees_registered_keys() {
+        let mut builder = ContainerBuilder::<String, dyn Base>::new_case_insensitive();
+        builder
+            .register_service(key("secret"), Box::new(Counter(7)))
+            .deprecate(key("hidden"), key("secret"), Box::new(|_: &String, _: &String| {}))
+            .with_middleware(Box::new(|key: &String, _: &dyn Base| key != "secret"));
+        let cont = builder.build();
+        assert_not_found(cont.read_service::<Counter>(&key("secret")), "secret");
+        assert_not_found(cont.read_service::<Counter>(&key("SECRET")), "SECRET");
+        assert_not_found(cont.read_service::<Counter>(&key("hidden")), "hidden");
+    }
+
+    #[test]
+    fn get_mut_or_insert_with_skips_disabled_services() {
+        let mut cont = builder().build();
+        let counter = key("counter");
+        cont.set_enabled(&counter, false);
+        assert_not_found(cont.get_mut_or_insert_with(&counter, || Counter(5)), "counter");
+        cont.set_enabled(&counter, true);
+        assert_eq!(cont.get_mut_or_insert_with(&counter, || Counter(5)).unwrap().0, 1);
+    }
+
+    #[test]
+    fn get_or_create_skips_disabled_services() {
+        let mut cont = builder().build();
+        cont.set_enabled(&key("counter"), false);
+        assert_not_found(cont.get_or_create(|| Counter(5)), "counter");
+        assert!(cont.get_service(&key("counter")).is_some());
+    }
+
+    #[test]
+    fn get_disjoint_mut_skips_disabled_services() {
+        let mut builder = builder();
+        builder.with_middleware(Box::new(|key: &String, _: &dyn Base| key != "name"));
+        let mut cont = builder.build();
+        assert_not_found(cont.get_disjoint_mut::<Counter, Name>(), "name");
+        cont.set_enabled(&key("counter"), false);
+        assert_not_found(cont.get_disjoint_mut::<Counter, Name>(), "counter");
+    }
+
+    #[test]
+    fn type_cache_notices_enabled_services() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let open = Arc::new(AtomicBool::new(false));
+        let gate = open.clone();
+        let mut builder = builder();
+        builder
+            .with_type_cache()
+            .register_service(key("other"), Box::new(Counter(2)))
+            .register_gated(key("name.beta"), Box::new(Name(key("beta"))), move || gate.load(Ordering::SeqCst));
+        let mut cont = builder.build();
+        let other = key("other");
+        cont.set_enabled(&other, false);
+        assert_eq!(cont.read_unique::<Counter>().unwrap().0, 1);
+        cont.set_enabled(&other, true);
+        assert!(cont.read_unique::<Counter>().is_err());
+
+        assert_eq!(cont.read_unique::<Name>().unwrap().0, "ioc");
+        open.store(true, Ordering::SeqCst);
+        assert!(cont.read_unique::<Name>().is_err());
+    }
 }
@@ -27,24 +27,56 @@ pub enum Error<'a, Key: 'a> {
     Poisoned{ key: &'a Key },
     WouldBlock{ key: &'a Key },
     MismatchedType{ key: &'a Key, expected: &'static str, found: &'static str },
-    CreationError{ key: &'a Key, error: Box<StdError> }
+    Ambiguous{ key: &'a Key },
+    CreationError{ key: &'a Key, error: Box<dyn StdError> }
+}
+
+impl<'a, Key> Error<'a, Key> {
+    fn message(&self) -> &'static str {
+        match *self {
+            Error::NotFound{ .. } => "Service could not be found",
+            Error::Poisoned{ .. } => "Service could not be aquired, mutex was poisoned",
+            Error::WouldBlock{ .. } => "Service could not be aquired, mutex would block",
+            Error::MismatchedType{ .. } => "Service is of wrong type",
+            Error::Ambiguous{ .. } => "Multiple services are of the requested type",
+            Error::CreationError{ .. } => "Factory failed to create object",
+        }
+    }
+
+    /// Maps the error to an HTTP-like status code, e.g. for reporting it over an admin API:
+    ///
+    /// - `NotFound`: 404
+    /// - `MismatchedType`: 409
+    /// - `Ambiguous`: 300
+    /// - `WouldBlock`: 503
+    /// - `Poisoned`, `CreationError`: 500
+    pub fn kind_code(&self) -> u16 {
+        match *self {
+            Error::NotFound{ .. } => 404,
+            Error::MismatchedType{ .. } => 409,
+            Error::Ambiguous{ .. } => 300,
+            Error::WouldBlock{ .. } => 503,
+            Error::Poisoned{ .. } | Error::CreationError{ .. } => 500,
+        }
+    }
 }
 
 impl<'a, Key> Display for Error<'a, Key>
     where Key: reflect::Key
 {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        let desc = self.description();
-        match self {
-            &Error::NotFound{ key } 
-            | &Error::Poisoned{ key } 
-            | &Error::WouldBlock{ key } => {
+        let desc = self.message();
+        match *self {
+            Error::NotFound{ key } 
+            | Error::Poisoned{ key } 
+            | Error::WouldBlock{ key }
+            | Error::Ambiguous{ key } => {
                 fmt.write_fmt(format_args!("[{:?}] {}.", key, desc))
             }
-            &Error::MismatchedType{ key, expected, found } => {
+            Error::MismatchedType{ key, expected, found } => {
                 fmt.write_fmt(format_args!("[{:?}] {}: Expected '{}' found '{}'.", key, desc, expected, found))
             }
-            &Error::CreationError{ key, ref error } => {
+            Error::CreationError{ key, ref error } => {
                 fmt.write_fmt(format_args!("[{:?}] {}: {}.", key, desc, error))
             }
         }
@@ -55,13 +87,7 @@ impl<'a, Key> StdError for Error<'a, Key>
     where Key: reflect::Key
 {
     fn description(&self) -> &str {
-        match self {
-            &Error::NotFound{ .. } => "Service could not be found",
-            &Error::Poisoned{ .. } => "Service could not be aquired, mutex was poisoned",
-            &Error::WouldBlock{ .. } => "Service could not be aquired, mutex would block",
-            &Error::MismatchedType{ .. } => "Service is of wrong type",
-            &Error::CreationError{ .. } => "Factory failed to create object",
-        }
+        self.message()
     }
 }
 
@@ -69,7 +95,7 @@ impl<'a, Key, X> From<(&'a Key, PoisonError<X>)> for Error<'a, Key>
     where Key: reflect::Key
 {
     fn from((key, _): (&'a Key, PoisonError<X>)) -> Self {
-        Error::Poisoned{ key: key }
+        Error::Poisoned{ key }
     }
 }
 
@@ -78,25 +104,171 @@ impl<'a, Key, X> From<(&'a Key, TryLockError<X>)> for Error<'a, Key>
 {
     fn from((key, err): (&'a Key, TryLockError<X>)) -> Self {
         match err {
-            TryLockError::Poisoned(_) => Error::Poisoned{ key: key },
-            TryLockError::WouldBlock => Error::WouldBlock{ key: key }
+            TryLockError::Poisoned(_) => Error::Poisoned{ key },
+            TryLockError::WouldBlock => Error::WouldBlock{ key }
+        }
+    }
+}
+
+// ++++++++++++++++++++ RenameError ++++++++++++++++++++
+
+#[derive(Debug)]
+pub enum RenameError<'a, Key: 'a> {
+    NotFound{ key: &'a Key },
+    AlreadyExists{ key: Key },
+}
+
+impl<'a, Key> RenameError<'a, Key> {
+    fn message(&self) -> &'static str {
+        match *self {
+            RenameError::NotFound{ .. } => "Service to be renamed could not be found",
+            RenameError::AlreadyExists{ .. } => "Service with the new key already exists",
+        }
+    }
+}
+
+impl<'a, Key> Display for RenameError<'a, Key>
+    where Key: reflect::Key
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RenameError::NotFound{ key } => {
+                fmt.write_fmt(format_args!("[{:?}] {}.", key, self.message()))
+            }
+            RenameError::AlreadyExists{ ref key } => {
+                fmt.write_fmt(format_args!("[{:?}] {}.", key, self.message()))
+            }
+        }
+    }
+}
+
+impl<'a, Key> StdError for RenameError<'a, Key> 
+    where Key: reflect::Key
+{
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
+
+// ++++++++++++++++++++ TransferError ++++++++++++++++++++
+
+#[derive(Debug)]
+pub enum TransferError<'a, Key: 'a> {
+    NotFound{ key: &'a Key },
+    AlreadyExists{ key: &'a Key },
+}
+
+impl<'a, Key> TransferError<'a, Key> {
+    fn message(&self) -> &'static str {
+        match *self {
+            TransferError::NotFound{ .. } => "Service to be transferred could not be found",
+            TransferError::AlreadyExists{ .. } => "Service with the same key already exists in the destination",
+        }
+    }
+}
+
+impl<'a, Key> Display for TransferError<'a, Key>
+    where Key: reflect::Key
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TransferError::NotFound{ key } | TransferError::AlreadyExists{ key } => {
+                fmt.write_fmt(format_args!("[{:?}] {}.", key, self.message()))
+            }
+        }
+    }
+}
+
+impl<'a, Key> StdError for TransferError<'a, Key> 
+    where Key: reflect::Key
+{
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
+
+// ++++++++++++++++++++ BuildError ++++++++++++++++++++
+
+#[derive(Debug)]
+pub enum BuildError<'a, Key: 'a> {
+    UnknownType{ key: &'a Key, type_name: &'a str },
+}
+
+impl<'a, Key> BuildError<'a, Key> {
+    fn message(&self) -> &'static str {
+        match *self {
+            BuildError::UnknownType{ .. } => "No constructor registered for type",
+        }
+    }
+}
+
+impl<'a, Key> Display for BuildError<'a, Key>
+    where Key: reflect::Key
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BuildError::UnknownType{ key, type_name } => {
+                fmt.write_fmt(format_args!("[{:?}] {} '{}'.", key, self.message(), type_name))
+            }
+        }
+    }
+}
+
+impl<'a, Key> StdError for BuildError<'a, Key> 
+    where Key: reflect::Key
+{
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
+
+// ++++++++++++++++++++ KeyError ++++++++++++++++++++
+
+#[derive(Debug)]
+pub enum KeyError<'a> {
+    Empty,
+    Reserved{ key: &'a str, reserved: char },
+}
+
+impl<'a> KeyError<'a> {
+    fn message(&self) -> &'static str {
+        match *self {
+            KeyError::Empty => "Key must not be empty",
+            KeyError::Reserved{ .. } => "Key contains reserved character",
         }
     }
 }
 
+impl<'a> Display for KeyError<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            KeyError::Empty => fmt.write_fmt(format_args!("{}.", self.message())),
+            KeyError::Reserved{ key, reserved } => {
+                fmt.write_fmt(format_args!("[{:?}] {} '{}'.", key, self.message(), reserved))
+            }
+        }
+    }
+}
+
+impl<'a> StdError for KeyError<'a> {
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
+
 // ++++++++++++++++++++ utility ++++++++++++++++++++
 
 /// Utility for converting `Result<X, [Poison|TryLock]Error>` to `Result<X, ioc::Error>`.
 ///
 /// Example usage:
 /// 
-/// ```
+/// ```ignore
 /// fn foo<'a>(bar: &'a RwLock<Svc>) -> Result<Foo, ioc::Error<'a, Key>> {
 ///     // doesn't work due to ioc::Error requiring the service key
-///     let foo = try!{lock.read()}.do_something();
+///     let foo = lock.read()?.do_something();
 ///
 ///     // works
-///     let foo = try!{ioc::or_err(Svc::key(), lock.read())}.do_something();
+///     let foo = ioc::or_err(Svc::key(), lock.read())?.do_something();
 ///     
 ///     Ok(foo)
 /// }
@@ -109,3 +281,20 @@ pub fn or_err<'a, Key, X, E>(key: &'a Key, res: Result<X, E>) -> Result<X, Error
         Err(err) => Err(Error::from((key, err)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_codes() {
+        let key = "svc".to_owned();
+        let key = &key;
+        assert_eq!(Error::NotFound{ key }.kind_code(), 404);
+        assert_eq!(Error::Poisoned{ key }.kind_code(), 500);
+        assert_eq!(Error::WouldBlock{ key }.kind_code(), 503);
+        assert_eq!(Error::MismatchedType{ key, expected: "A", found: "B" }.kind_code(), 409);
+        assert_eq!(Error::Ambiguous{ key }.kind_code(), 300);
+        assert_eq!(Error::CreationError{ key, error: Box::new(DummyError(())) }.kind_code(), 500);
+    }
+}
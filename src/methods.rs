@@ -69,12 +69,12 @@ macro_rules! multi_read {
             type Ret = ($(<Read<$params> as Method<'a, Key, SvcBase>>::Ret,)+);
             fn resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
                 Ok((
-                    $(try!{ioc.read::<$params>()},)+
+                    $(ioc.read::<$params>()?,)+
                 ))
             }
             fn try_resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
                 Ok((
-                    $(try!{ioc.try_read::<$params>()},)+
+                    $(ioc.try_read::<$params>()?,)+
                 ))
             }
         }
@@ -132,12 +132,12 @@ macro_rules! multi_write {
             type Ret = ($(<Write<$params> as Method<'a, Key, SvcBase>>::Ret,)+);
             fn resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
                 Ok((
-                    $(try!{ioc.write::<$params>()},)+
+                    $(ioc.write::<$params>()?,)+
                 ))
             }
             fn try_resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
                 Ok((
-                    $(try!{ioc.try_write::<$params>()},)+
+                    $(ioc.try_write::<$params>()?,)+
                 ))
             }
         }
@@ -163,6 +163,32 @@ multi_write!{
     {A B C D E F G H J K L M N O P Q}
 }
 
+// ++++++++++++++++++++ Option ++++++++++++++++++++
+
+/// Resolves to `None` instead of failing if the service isn't registered.
+impl<'a, Key, SvcBase: ?Sized, M> Method<'a, Key, SvcBase> for Option<M>
+where 
+    Key: reflect::Key,
+    M: Method<'a, Key, SvcBase>,
+    SvcBase: Any,
+{
+    type Ret = Option<M::Ret>;
+    fn resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
+        match M::resolve_unprotected(ioc) {
+            Ok(ret) => Ok(Some(ret)),
+            Err(Error::NotFound{ .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    fn try_resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
+        match M::try_resolve_unprotected(ioc) {
+            Ok(ret) => Ok(Some(ret)),
+            Err(Error::NotFound{ .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
 /*
 // ++++++++++++++++++++ Create ++++++++++++++++++++
 
@@ -193,7 +219,7 @@ macro_rules! multi_create {
             type Ret = ($(<Create<$params> as Method<'a, Cont>>::Ret,)+);
             fn resolve_unprotected(ioc: &'a Cont) -> Result<Self::Ret, Error<'a, Cont::Key>> {
                 Ok((
-                    $(try!{ioc.create::<$params>()},)+
+                    $(ioc.create::<$params>()?,)+
                 ))
             }
         }
@@ -202,10 +228,6 @@ macro_rules! multi_create {
 */
 // ++++++++++++++++++++ multi-method ++++++++++++++++++++
 
-macro_rules! e {
-    ($e:expr) => { $e }
-}
-
 macro_rules! multi_methods {
     ($({$($idx:tt,$params:ident)+})+) => {$(
         
@@ -218,12 +240,12 @@ macro_rules! multi_methods {
             type Ret = ($($params::Ret,)+);
             fn resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
                 Ok((
-                    $(try!{e![$params::resolve_unprotected(ioc)]},)+
+                    $($params::resolve_unprotected(ioc)?,)+
                 ))
             }
             fn try_resolve_unprotected(ioc: &'a Container<Key, SvcBase>) -> Result<Self::Ret, Error<'a, Key>> {
                 Ok((
-                    $(try!{e![$params::try_resolve_unprotected(ioc)]},)+
+                    $($params::try_resolve_unprotected(ioc)?,)+
                 ))
             }
         }
@@ -250,6 +272,67 @@ multi_methods!{
     {0,A 1,B 2,C 3,D 4,E 5,F 6,G 7,H 8,J 9,K 10,L 11,M 12,N 13,O 14,P 15,Q}
 }
 
+// ++++++++++++++++++++ from_container ++++++++++++++++++++
+
+/// Declares a struct whose fields are resolved from a container in one go, with each field 
+/// given as a `Method` (e.g. `Read<Svc>`, `Write<Svc>` or `Option<Read<Svc>>`):
+///
+/// ```ignore
+/// from_container!{
+///     pub struct Deps<'a>: Container<String, dyn Base> {
+///         pub logger: Read<Logger>,
+///         pub cache: Option<Write<Cache>>,
+///     }
+/// }
+///
+/// let deps = Deps::from_container(&ioc)?;
+/// ```
+///
+/// NOTE: Supports up to 16 fields, like `Method` for tuples.
+#[macro_export]
+macro_rules! from_container {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident<$lt:lifetime>: Container<$key:ty, $base:ty> {
+            $($fvis:vis $field:ident: $method:ty),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name<$lt> {
+            $($fvis $field: <$method as $crate::Method<$lt, $key, $base>>::Ret),+
+        }
+
+        impl<$lt> $name<$lt> {
+            pub fn from_container(
+                ioc: &$lt $crate::Container<$key, $base>
+            ) -> Result<Self, $crate::Error<$lt, $key>> {
+                let ($($field,)+) = ioc.resolve::<($($method,)+)>()?;
+                Ok($name{ $($field),+ })
+            }
+        }
+    };
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use container::tests::*;
 
+    from_container!{
+        struct Deps<'a>: Container<String, dyn Base> {
+            counter: Write<Counter>,
+            name: Option<Read<Name>>,
+            foo: Option<Read<Box<dyn Foo>>>,
+        }
+    }
 
+    #[test]
+    fn from_container_resolves_fields() {
+        let cont = builder().build();
+        let mut deps = Deps::from_container(&cont).unwrap();
+        deps.counter.0 += 1;
+        assert_eq!(deps.counter.0, 2);
+        assert_eq!(deps.name.unwrap().0, "ioc");
+        assert!(deps.foo.is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use errors::{self, Error, KeyError};
+use container::{Container, ContainerBuilder, ReadGuard, Resolver, WriteGuard};
+
+use downcast::Downcast;
+
+use std::any::Any;
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+/// Separates the prefix of a namespace from the keys within it, hence reserved in keys 
+/// registered through a `NamespaceBuilder`.
+pub const SEPARATOR: char = '.';
+
+fn prefixed(prefix: &str, key: &str) -> String {
+    format!("{}{}{}", prefix, SEPARATOR, key)
+}
+
+// ++++++++++++++++++++ NamespaceBuilder ++++++++++++++++++++
+
+/// Registers services with keys relative to a prefix, see `ContainerBuilder::namespace`.
+pub struct NamespaceBuilder<'b, SvcBase: ?Sized + 'b> {
+    builder: &'b mut ContainerBuilder<String, SvcBase>,
+    prefix: String,
+}
+
+impl<'b, SvcBase: ?Sized> NamespaceBuilder<'b, SvcBase>
+    where SvcBase: Any
+{
+    /// Registers `svc` under `"<prefix>.<key>"`.
+    pub fn register_service(&mut self, key: &str, svc: Box<SvcBase>) -> &mut Self {
+        self.builder.register_service(prefixed(&self.prefix, key), svc);
+        self
+    }
+
+    /// Like `register_service`, but rejects empty keys and keys containing `SEPARATOR`, which 
+    /// would end up in (or collide with a key in) a nested namespace.
+    pub fn try_register_service<'k>(&mut self, key: &'k str, svc: Box<SvcBase>) -> Result<&mut Self, KeyError<'k>> {
+        if key.is_empty() {
+            return Err(KeyError::Empty);
+        }
+        if key.contains(SEPARATOR) {
+            return Err(KeyError::Reserved{ key, reserved: SEPARATOR });
+        }
+        Ok(self.register_service(key, svc))
+    }
+
+    /// Opens a namespace nested in this one.
+    pub fn namespace(&mut self, prefix: &str) -> NamespaceBuilder<'_, SvcBase> {
+        NamespaceBuilder{ builder: self.builder, prefix: prefixed(&self.prefix, prefix) }
+    }
+}
+
+impl<SvcBase: ?Sized> ContainerBuilder<String, SvcBase>
+    where SvcBase: Any
+{
+    /// Opens a namespace whose services get registered as `"<prefix>.<key>"`.
+    pub fn namespace(&mut self, prefix: &str) -> NamespaceBuilder<'_, SvcBase> {
+        NamespaceBuilder{ builder: self, prefix: prefix.to_owned() }
+    }
+}
+
+// ++++++++++++++++++++ NamespacedView ++++++++++++++++++++
+
+/// Looks up services with keys relative to a prefix, see `Container::within`.
+///
+/// Errors report the relative key.
+pub struct NamespacedView<'c, SvcBase: ?Sized + 'c> {
+    cont: &'c Container<String, SvcBase>,
+    prefix: String,
+}
+
+impl<'c, SvcBase: ?Sized> NamespacedView<'c, SvcBase>
+    where SvcBase: Any
+{
+    pub fn read_service_base<'a>(
+        &'a self, 
+        key: &'a String
+    ) -> Result<RwLockReadGuard<'a, Box<SvcBase>>, Error<'a, String>> {
+        let full = prefixed(&self.prefix, key);
+        match self.cont.get_service(&full) {
+            Some(service) => {
+                let svc = errors::or_err(key, service.read())?;
+                if self.cont.admits(&full, &**svc) {
+                    Ok(svc)
+                } else {
+                    Err(Error::NotFound{ key })
+                }
+            }
+            None => Err(Error::NotFound{ key })
+        }
+    }
+
+    pub fn write_service_base<'a>(
+        &'a self, 
+        key: &'a String
+    ) -> Result<RwLockWriteGuard<'a, Box<SvcBase>>, Error<'a, String>> {
+        let full = prefixed(&self.prefix, key);
+        match self.cont.get_service(&full) {
+            Some(service) => {
+                let svc = errors::or_err(key, service.write())?;
+                if self.cont.admits(&full, &**svc) {
+                    Ok(svc)
+                } else {
+                    Err(Error::NotFound{ key })
+                }
+            }
+            None => Err(Error::NotFound{ key })
+        }
+    }
+
+    pub fn read_service<'a, Svc>(
+        &'a self, 
+        key: &'a String
+    ) -> Result<ReadGuard<'a, Svc, SvcBase>, Error<'a, String>>
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        (self as &dyn Resolver<String, SvcBase>).read_service(key)
+    }
+
+    pub fn write_service<'a, Svc>(
+        &'a self, 
+        key: &'a String
+    ) -> Result<WriteGuard<'a, Svc, SvcBase>, Error<'a, String>>
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        (self as &dyn Resolver<String, SvcBase>).write_service(key)
+    }
+
+    /// Narrows the view down to a nested namespace.
+    pub fn within(&self, prefix: &str) -> NamespacedView<'c, SvcBase> {
+        NamespacedView{ cont: self.cont, prefix: prefixed(&self.prefix, prefix) }
+    }
+}
+
+impl<'c, SvcBase: ?Sized> Resolver<String, SvcBase> for NamespacedView<'c, SvcBase>
+    where SvcBase: Any
+{
+    fn read_service_base<'a>(
+        &'a self, 
+        key: &'a String
+    ) -> Result<RwLockReadGuard<'a, Box<SvcBase>>, Error<'a, String>> {
+        NamespacedView::read_service_base(self, key)
+    }
+
+    fn write_service_base<'a>(
+        &'a self, 
+        key: &'a String
+    ) -> Result<RwLockWriteGuard<'a, Box<SvcBase>>, Error<'a, String>> {
+        NamespacedView::write_service_base(self, key)
+    }
+}
+
+impl<SvcBase: ?Sized> Container<String, SvcBase>
+    where SvcBase: Any
+{
+    /// Returns a view which looks up `key` as `"<prefix>.<key>"`.
+    pub fn within(&self, prefix: &str) -> NamespacedView<'_, SvcBase> {
+        NamespacedView{ cont: self, prefix: prefix.to_owned() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use container::tests::*;
+
+    #[test]
+    fn register_and_resolve_within_namespace() {
+        let mut builder = builder();
+        {
+            let mut audio = builder.namespace("audio");
+            audio.register_service("counter", Box::new(Counter(48)));
+            audio.namespace("mixer").register_service("name", Box::new(Name(key("main"))));
+        }
+        let cont = builder.build();
+
+        let audio = cont.within("audio");
+        assert_eq!(audio.read_service::<Counter>(&key("counter")).unwrap().0, 48);
+        assert_eq!(audio.within("mixer").read_service::<Name>(&key("name")).unwrap().0, "main");
+        assert_eq!(cont.read_service::<Counter>(&key("audio.counter")).unwrap().0, 48);
+        assert_eq!(cont.read_service::<Name>(&key("audio.mixer.name")).unwrap().0, "main");
+        assert_eq!(cont.read::<Counter>().unwrap().0, 1);
+
+        assert_not_found(audio.read_service_base(&key("video")), "video");
+    }
+
+    #[test]
+    fn try_register_service_rejects_invalid_keys() {
+        let mut builder = builder();
+        let mut audio = builder.namespace("audio");
+        assert!(audio.try_register_service("counter", Box::new(Counter(48))).is_ok());
+        match audio.try_register_service("", Box::new(Counter(0))) {
+            Err(KeyError::Empty) => {}
+            Err(err) => panic!("expected `Empty`, got {}", err),
+            Ok(_) => panic!("expected `Empty`"),
+        };
+        match audio.try_register_service("mixer.name", Box::new(Name(key("main")))) {
+            Err(KeyError::Reserved{ key, reserved }) => assert_eq!((key, reserved), ("mixer.name", '.')),
+            Err(err) => panic!("expected `Reserved`, got {}", err),
+            Ok(_) => panic!("expected `Reserved`"),
+        };
+        assert_eq!(builder.build().services().len(), 3);
+    }
+
+    #[test]
+    fn middleware_applies_within_namespace() {
+        let mut builder = builder();
+        builder.namespace("audio").register_service("counter", Box::new(Counter(48)));
+        builder.with_middleware(Box::new(|key: &String, _: &dyn Base| key != "audio.counter"));
+        let cont = builder.build();
+        let audio = cont.within("audio");
+        assert_not_found(audio.read_service::<Counter>(&key("counter")), "counter");
+        assert!(audio.write_service::<Counter>(&key("counter")).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use container::Container;
+use reflect;
+
+use downcast::Downcast;
+
+use std::any::Any;
+use std::sync::{PoisonError, RwLock};
+
+type Filter<'c, Key, SvcBase> = Box<dyn Fn(&Key, &RwLock<Box<SvcBase>>) -> bool + 'c>;
+
+// ++++++++++++++++++++ Query ++++++++++++++++++++
+
+/// Looks up keys by chaining filters, see `Container::query`. The filters are only run once
+/// the query gets evaluated by `iter`, `collect` or `first`, in the order they were added.
+pub struct Query<'c, Key: Ord + 'c, SvcBase: ?Sized + 'c> {
+    cont: &'c Container<Key, SvcBase>,
+    filters: Vec<Filter<'c, Key, SvcBase>>,
+}
+
+impl<'c, Key, SvcBase: ?Sized> Query<'c, Key, SvcBase>
+    where Key: reflect::Key, SvcBase: Any
+{
+    /// Keeps the keys for which `f` returns `true`.
+    pub fn filter_key<F>(mut self, f: F) -> Self
+        where F: Fn(&Key) -> bool + 'c
+    {
+        self.filters.push(Box::new(move |key, _| f(key)));
+        self
+    }
+
+    /// Keeps the services tagged with `tag`, see `ContainerBuilder::register_tagged`.
+    pub fn tagged(mut self, tag: &'c str) -> Self {
+        let cont = self.cont;
+        self.filters.push(Box::new(move |key, _| cont.has_tag(key, tag)));
+        self
+    }
+
+    /// Keeps the services which aren't poisoned.
+    pub fn healthy(mut self) -> Self {
+        self.filters.push(Box::new(|_, svc| !svc.is_poisoned()));
+        self
+    }
+
+    /// Keeps the services of type `Svc`. Read-locks every service it checks.
+    pub fn of_type<Svc>(mut self) -> Self
+        where Svc: Any, SvcBase: Downcast<Svc>
+    {
+        self.filters.push(Box::new(|_, svc| {
+            let svc = svc.read().unwrap_or_else(PoisonError::into_inner);
+            Downcast::<Svc>::is_type(&**svc)
+        }));
+        self
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = &'c Key> {
+        let filters = self.filters;
+        self.cont.services().iter()
+            .filter(move |&(key, svc)| filters.iter().all(|f| f(key, svc)))
+            .map(|(key, _)| key)
+    }
+
+    pub fn collect(self) -> Vec<&'c Key> {
+        self.iter().collect()
+    }
+
+    pub fn first(self) -> Option<&'c Key> {
+        self.iter().next()
+    }
+}
+
+impl<'c, SvcBase: ?Sized> Query<'c, String, SvcBase>
+    where SvcBase: Any
+{
+    /// Keeps the keys starting with `prefix`.
+    pub fn prefix(self, prefix: &'c str) -> Self {
+        self.filter_key(move |key| key.starts_with(prefix))
+    }
+}
+
+impl<Key, SvcBase: ?Sized> Container<Key, SvcBase>
+    where Key: reflect::Key, SvcBase: Any
+{
+    /// Starts a query over the keys of all services.
+    pub fn query(&self) -> Query<'_, Key, SvcBase> {
+        Query{ cont: self, filters: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use container::tests::*;
+
+    #[test]
+    fn combined_filters() {
+        let mut builder = builder();
+        let core = vec![key("core")].into_iter().collect();
+        builder
+            .register_service(key("audio.volume"), Box::new(Counter(3)))
+            .register_service(key("audio.device"), Box::new(Name(key("hw:0"))))
+            .register_tagged(key("audio.rate"), Box::new(Counter(48)), core);
+        let cont = builder.build();
+        assert_eq!(cont.query().prefix("audio.").of_type::<Counter>().collect(), vec!["audio.rate", "audio.volume"]);
+        assert_eq!(cont.query().of_type::<Counter>().tagged("core").healthy().first().unwrap(), "audio.rate");
+        assert!(cont.query().prefix("video.").first().is_none());
+    }
+}
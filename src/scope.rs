@@ -0,0 +1,80 @@
+use container::Container;
+use reflect;
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+
+// ++++++++++++++++++++ scope ++++++++++++++++++++
+
+// Innermost scope last; the `TypeId` is the one of the `Container<..>` behind the pointer.
+thread_local!{
+    static CURRENT: RefCell<Vec<(TypeId, *const ())>> = const { RefCell::new(Vec::new()) };
+}
+
+struct PopOnDrop;
+
+impl Drop for PopOnDrop {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().pop());
+    }
+}
+
+impl<Key, SvcBase: ?Sized> Container<Key, SvcBase> 
+    where Key: reflect::Key, SvcBase: Any
+{
+    /// Makes this container the current one of its type on this thread while `f` runs, so 
+    /// code further down the stack can get at it via `with_current`. Scopes can be nested.
+    ///
+    /// NOTE: This is the service-locator pattern: it saves threading the container through 
+    /// every call, but hides which services a function depends on and ties it to the thread.
+    pub fn scope<R, F>(&self, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        let ptr = self as *const Self as *const ();
+        CURRENT.with(|current| current.borrow_mut().push((TypeId::of::<Self>(), ptr)));
+        let _pop = PopOnDrop;
+        f()
+    }
+}
+
+/// Hands the innermost container set via `Container::scope` to `f`, or returns `None` if no 
+/// container of this type is current on this thread.
+pub fn with_current<Key, SvcBase, R, F>(f: F) -> Option<R>
+    where Key: reflect::Key, SvcBase: ?Sized + Any, F: FnOnce(&Container<Key, SvcBase>) -> R
+{
+    let wanted = TypeId::of::<Container<Key, SvcBase>>();
+    let ptr = CURRENT.with(|current| {
+        current.borrow().iter().rev().find(|&&(id, _)| id == wanted).map(|&(_, ptr)| ptr)
+    })?;
+    // The pointer stays valid as long as it's on the stack: `scope` borrows the container for 
+    // the whole time it's in there, and pops it before returning (or unwinding).
+    let cont = unsafe { &*(ptr as *const Container<Key, SvcBase>) };
+    Some(f(cont))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use container::ContainerBuilder;
+    use container::tests::*;
+
+    fn current_counter() -> Option<i32> {
+        with_current(|cont: &Container<String, dyn Base>| cont.read::<Counter>().unwrap().0)
+    }
+
+    #[test]
+    fn nested_scopes() {
+        let outer = builder().build();
+        let mut inner = ContainerBuilder::<String, dyn Base>::new();
+        inner.register(Counter(2));
+        let inner = inner.build();
+
+        assert_eq!(current_counter(), None);
+        outer.scope(|| {
+            assert_eq!(current_counter(), Some(1));
+            inner.scope(|| assert_eq!(current_counter(), Some(2)));
+            assert_eq!(current_counter(), Some(1));
+        });
+        assert_eq!(current_counter(), None);
+    }
+}
@@ -0,0 +1,61 @@
+//! Assertions for test suites checking how a container is set up, enabled by the `testing` 
+//! feature.
+
+/// Asserts that a service of type `$svc` is registered under `$key`:
+///
+/// ```ignore
+/// assert_registered!(ioc, &key("logger") => Logger);
+/// ```
+#[macro_export]
+macro_rules! assert_registered {
+    ($cont:expr, $key:expr => $svc:ty) => {
+        match $key {
+            key => match $cont.read_service::<$svc>(key) {
+                Ok(_) => {}
+                Err(err) => panic!("expected a `{}` under {:?}: {}", stringify!($svc), key, err),
+            }
+        }
+    };
+}
+
+/// Asserts that nothing is registered under `$key`:
+///
+/// ```ignore
+/// assert_missing!(ioc, &key("legacy.logger"));
+/// ```
+#[macro_export]
+macro_rules! assert_missing {
+    ($cont:expr, $key:expr) => {
+        match $key {
+            key => if $cont.contains(key) {
+                panic!("expected nothing under {:?}", key);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use container::tests::*;
+
+    #[test]
+    fn assertions_pass() {
+        let cont = builder().build();
+        assert_registered!(cont, &key("counter") => Counter);
+        assert_missing!(cont, &key("logger"));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a `Name` under \"counter\"")]
+    fn assert_registered_catches_wrong_type() {
+        let cont = builder().build();
+        assert_registered!(cont, &key("counter") => Name);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected nothing under \"name\"")]
+    fn assert_missing_catches_registered() {
+        let cont = builder().build();
+        assert_missing!(cont, &key("name"));
+    }
+}